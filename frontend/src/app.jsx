@@ -9,6 +9,9 @@ export default function App() {
   const [projectKey, setProjectKey] = useState(null);
   const [darkMode, setDarkMode] = useState(true);
   const [fullscreen, setFullscreen] = useState(true);
+  const [resumedSession, setResumedSession] = useState(null);
+  // The metadata of the sessions we start, which the facilitator fills in when starting a project
+  const [sessionMetadata, setSessionMetadata] = useState({});
   const project = useMemo(
     () => projects.find((p) => p.key === projectKey),
     [projects, projectKey],
@@ -51,12 +54,18 @@ export default function App() {
       project={project}
       language={language}
       darkMode={darkMode}
+      resumedSession={resumedSession}
+      sessionMetadata={sessionMetadata}
+      setSessionMetadata={setSessionMetadata}
       resetProject={() => setProjectKey(null)}
     />
   ) : (
     <StartScreen
       setProjectKey={setProjectKey}
       setDarkMode={setDarkMode}
+      setResumedSession={setResumedSession}
+      sessionMetadata={sessionMetadata}
+      setSessionMetadata={setSessionMetadata}
       language={language}
       toggleFullScreen={toggleFullScreen}
       projects={projects}
@@ -0,0 +1,147 @@
+import { useState } from "react";
+import { invoke } from "@tauri-apps/api/tauri";
+import translate from "../../locales";
+
+const STATES = {
+  idle: "IDLE",
+  working: "WORKING",
+  error: "ERROR",
+  done: "DONE",
+};
+
+const FIELDS = [
+  { name: "location", type: "text" },
+  { name: "facilitator", type: "text" },
+  { name: "groupName", type: "text" },
+  { name: "ageRange", type: "text", placeholder: "12-14" },
+  { name: "expectedParticipants", type: "number" },
+  { name: "notes", type: "textarea" },
+];
+
+/**
+ * Read the metadata of a session from a form with `SessionMetadataFields`
+ *
+ * Empty fields are left out, so they are cleared in the backend.
+ */
+export function readSessionMetadata(data) {
+  const metadata = {};
+  for (const { name, type } of FIELDS) {
+    const value = data.get(name)?.trim();
+    if (value) {
+      metadata[name] = type === "number" ? parseInt(value, 10) : value;
+    }
+  }
+  return metadata;
+}
+
+/**
+ * The inputs for the metadata of a session, which are all optional
+ */
+export function SessionMetadataFields({ metadata }) {
+  return FIELDS.map(({ name, type, placeholder }) => (
+    <div className="dialog__input" key={name}>
+      <label className="dialog__label" htmlFor={name}>
+        {translate(`session_metadata_${name}`)}
+      </label>
+      {type === "textarea" ? (
+        <textarea
+          className="dialog__input-field"
+          name={name}
+          id={name}
+          defaultValue={metadata?.[name] ?? ""}
+        />
+      ) : (
+        <input
+          className="dialog__input-field"
+          type={type}
+          name={name}
+          id={name}
+          min={type === "number" ? 0 : undefined}
+          placeholder={placeholder}
+          defaultValue={metadata?.[name] ?? ""}
+        />
+      )}
+    </div>
+  ));
+}
+
+/**
+ * Edit the metadata of the current session, e.g. to add notes while it is running
+ *
+ * The form is only shown once `metadata` is loaded, so it starts with the saved values.
+ */
+export default function SessionMetadataDialog({
+  metadataDialog,
+  sessionID,
+  metadata,
+  onSaved,
+  onClose,
+}) {
+  const [state, setState] = useState(STATES.idle);
+
+  async function handleSubmit(e) {
+    e.preventDefault();
+    setState(STATES.working);
+
+    const edited = readSessionMetadata(new FormData(e.target));
+    try {
+      await invoke("update_session_metadata", {
+        sessionId: sessionID,
+        metadata: edited,
+      });
+      onSaved(edited);
+      metadataDialog.current?.close();
+    } catch (error) {
+      setState(STATES.error);
+      // eslint-disable-next-line no-console
+      console.error("The session metadata couldn't be saved.", error);
+    }
+  }
+
+  return (
+    <dialog
+      className="dialog"
+      ref={metadataDialog}
+      onClose={() => {
+        setState(STATES.idle);
+        onClose();
+      }}
+    >
+      {metadata && (
+        <form
+          action=""
+          onSubmit={handleSubmit}
+          className="start-screen__popup"
+          disabled={state === STATES.working}
+        >
+          <h2 className="dialog__title">
+            {translate("session_metadata_title")}
+          </h2>
+          <SessionMetadataFields metadata={metadata} />
+          {state === STATES.error && (
+            <span className="dialog__message dialog__message--error">
+              {translate("session_metadata_error")}
+            </span>
+          )}
+          <div className="dialog__button-container">
+            <button
+              type="submit"
+              className="start-screen__button"
+              disabled={state === STATES.working}
+            >
+              {translate("save_button")}
+            </button>
+            <button
+              type="button"
+              className="start-screen__button start-screen__button--outline"
+              onClick={() => metadataDialog.current?.close()}
+              disabled={state === STATES.working}
+            >
+              {translate("cancel_button")}
+            </button>
+          </div>
+        </form>
+      )}
+    </dialog>
+  );
+}
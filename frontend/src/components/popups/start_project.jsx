@@ -2,9 +2,20 @@ import { useEffect, useState } from "react";
 import { invoke } from "@tauri-apps/api/tauri";
 import { listen } from "@tauri-apps/api/event";
 import translate, { translateError } from "../../locales";
+import {
+  readSessionMetadata,
+  SessionMetadataFields,
+} from "./session_metadata";
 
 const previousHostname = localStorage.getItem("circles.last_hostname");
 
+const HOSTNAME_PLACEHOLDERS = {
+  llrp: "fx9600123456",
+  serial: "/dev/ttyUSB0",
+  hid: "stdin",
+  mock: "demo",
+};
+
 const STATES = {
   idle: "IDLE",
   working: "WORKING",
@@ -16,15 +27,35 @@ export default function StartProject({
   setProjectKey,
   selectedProjectKey,
   setDarkMode,
+  setResumedSession,
+  sessionMetadata,
+  setSessionMetadata,
   startDialog,
 }) {
   const [state, setState] = useState(STATES.idle);
   const [error, setError] = useState(null);
-  const [connectionStatus, setConnectionStatus] = useState(null);
+  const [readerState, setReaderState] = useState(null);
+  const [backends, setBackends] = useState([]);
+  const [backend, setBackend] = useState("llrp");
+  const [resumableSession, setResumableSession] = useState(null);
+
+  useEffect(() => {
+    invoke("get_reader_backends").then(setBackends);
+    invoke("get_reader_backend").then(setBackend);
+  }, []);
 
   useEffect(() => {
-    const unlisten = listen("connection-status", ({ payload }) =>
-      setConnectionStatus(payload),
+    // A session that was interrupted (e.g. by a restart) can be continued, so its answers stay together
+    invoke("get_resumable_session").then((session) =>
+      setResumableSession(
+        session?.projectKey === selectedProjectKey ? session : null,
+      ),
+    );
+  }, [selectedProjectKey]);
+
+  useEffect(() => {
+    const unlisten = listen("reader-state", ({ payload }) =>
+      setReaderState(payload.state),
     );
 
     return () => unlisten.then((fn) => fn());
@@ -37,17 +68,27 @@ export default function StartProject({
 
     const data = new FormData(e.target);
     const projectKey = selectedProjectKey;
-    const hostname = data.get("hostname");
+    // The mock reader doesn't connect to anything, so it doesn't need a hostname
+    const hostname = data.get("hostname") || backend;
     const darkMode = data.get("darkMode");
+    const resume = resumableSession && data.get("resume");
     try {
-      await invoke("select_project", { projectKey, hostname });
+      await invoke("select_project", { projectKey, hostname, backend });
       localStorage.setItem("circles.last_hostname", hostname);
+      setResumedSession(
+        resume
+          ? await invoke("resume_session", {
+              sessionId: resumableSession.sessionId,
+            })
+          : null,
+      );
+      setSessionMetadata(readSessionMetadata(data));
       setDarkMode(darkMode);
       setProjectKey(projectKey);
     } catch (error) {
       setState(STATES.error);
       setError(error);
-      setConnectionStatus(null);
+      setReaderState(null);
       // If an unknown error occurs, we want to log the details so we can see what went wrong
       // eslint-disable-next-line no-console
       if (error.kind === "Unknown") console.error(error);
@@ -72,6 +113,24 @@ export default function StartProject({
         <span className="dialog__label">
           {translate("start_reader_hostname_subtitle")}
         </span>
+        <div className="dialog__input">
+          <label className="dialog__label" htmlFor="backend">
+            {translate("start_reader_backend")}
+          </label>
+          <select
+            className="dialog__input-field"
+            name="backend"
+            id="backend"
+            value={backend}
+            onChange={(e) => setBackend(e.target.value)}
+          >
+            {backends.map((name) => (
+              <option key={name} value={name}>
+                {translate(`reader_backend_${name}`)}
+              </option>
+            ))}
+          </select>
+        </div>
         <div className="dialog__input">
           <label className="dialog__label" htmlFor="hostname">
             {translate("start_reader_hostname")}
@@ -82,10 +141,11 @@ export default function StartProject({
             name="hostname"
             id="hostname"
             autoCapitalize="false"
-            placeholder="fx9600123456"
-            minLength={12}
-            maxLength={12}
-            required
+            placeholder={HOSTNAME_PLACEHOLDERS[backend]}
+            // Only the hostnames of our LLRP readers have a fixed length
+            minLength={backend === "llrp" ? 12 : undefined}
+            maxLength={backend === "llrp" ? 12 : undefined}
+            required={backend !== "mock"}
             defaultValue={previousHostname}
           />
         </div>
@@ -101,6 +161,24 @@ export default function StartProject({
             {translate("start_dark_mode")}
           </label>
         </div>
+        <span className="dialog__label">
+          {translate("session_metadata_subtitle")}
+        </span>
+        <SessionMetadataFields metadata={sessionMetadata} />
+        {resumableSession && (
+          <div className="dialog__input dialog__input--checkbox">
+            <input
+              type="checkbox"
+              name="resume"
+              id="resume"
+              className="dialog__checkbox"
+              defaultChecked={true}
+            />
+            <label htmlFor="resume" className="dialog__label">
+              {translate("start_resume_session")}
+            </label>
+          </div>
+        )}
         <div className="dialog__button-container">
           <button
             type="submit"
@@ -138,8 +216,10 @@ export default function StartProject({
             {translateError(error)}
           </span>
         )}
-        {connectionStatus && (
-          <span className="dialog__detail">{connectionStatus}</span>
+        {readerState && (
+          <span className="dialog__detail">
+            {translate(`reader_state_${readerState}`)}
+          </span>
         )}
       </form>
     </dialog>
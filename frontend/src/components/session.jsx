@@ -1,10 +1,11 @@
-import { useEffect, useState, useMemo } from "react";
+import { useEffect, useState, useMemo, useRef } from "react";
 import { invoke } from "@tauri-apps/api/tauri";
 import { InteractionScreen } from "./interaction-screen";
 import ControlPanel from "./control-panel";
 import { listen } from "@tauri-apps/api/event";
 import shuffle from "../utils/shuffle";
 import translate from "../locales";
+import SessionMetadataDialog from "./popups/session_metadata";
 
 export const STEPS = {
   showBigTitle: "showBigTitle",
@@ -23,15 +24,47 @@ function assignOptionColors(options) {
   }));
 }
 
-export default function Session({ project, resetProject, language, darkMode }) {
+/**
+ * The phase of the question after the last answered one, since phase 0 is choosing a theme
+ *
+ * Returns `null` when every question of the theme was answered already, so there is nothing left to resume.
+ */
+function phaseAfterStep(theme, lastStep) {
+  const answered = theme.questions.findIndex((q) => q.key === lastStep);
+  return answered + 2 <= theme.questions.length ? answered + 2 : null;
+}
+
+export default function Session({
+  project,
+  resetProject,
+  language,
+  darkMode,
+  resumedSession,
+  sessionMetadata,
+  setSessionMetadata,
+}) {
+  const sessionTheme = project.themes.find(
+    (t) => t.key === resumedSession?.themeKey,
+  );
+  const resumedPhase =
+    sessionTheme && phaseAfterStep(sessionTheme, resumedSession.lastStep);
+  const resumedTheme = resumedPhase ? sessionTheme : undefined;
   const [tagsMap, setTagsMap] = useState({});
   const [, setReaderError] = useState(null);
   const [error, setError] = useState(null);
-  const [sessionID, setSessionID] = useState(null);
-  const [phase, setPhase] = useState(0);
-  const [step, setStep] = useState(STEPS.showBigTitle);
+  const [sessionID, setSessionID] = useState(
+    resumedTheme ? resumedSession.sessionId : null,
+  );
+  const [phase, setPhase] = useState(resumedPhase || 0);
+  const [step, setStep] = useState(
+    resumedTheme ? STEPS.showBigQuestion : STEPS.showBigTitle,
+  );
   const [themes, setThemes] = useState(project.themes);
-  const [chosenTheme, setChosenTheme] = useState(null);
+  const [chosenTheme, setChosenTheme] = useState(resumedTheme ?? null);
+  // After undoing a step we continue its vote, so we shouldn't open a new one
+  const continueVote = useRef(false);
+  const metadataDialog = useRef();
+  const [editedMetadata, setEditedMetadata] = useState(null);
 
   const currentQuestion =
     chosenTheme !== null && chosenTheme.questions[phase - 1];
@@ -133,7 +166,10 @@ export default function Session({ project, resetProject, language, darkMode }) {
 
   async function startNewSession(themeKey) {
     try {
-      const response = await invoke("start_session", { themeKey });
+      const response = await invoke("start_session", {
+        themeKey,
+        metadata: sessionMetadata,
+      });
       setSessionID(response);
     } catch (e) {
       if (e === "Please select a project first") {
@@ -145,7 +181,8 @@ export default function Session({ project, resetProject, language, darkMode }) {
 
   async function saveAnswers() {
     try {
-      await invoke("save_step_results", { currentStep: currentQuestion.key });
+      // Answering a question again replaces its answers in the backend
+      await invoke("close_step", { currentStep: currentQuestion.key });
     } catch (error) {
       // The user cannot fix this error, so we just log it for debugging
       // eslint-disable-next-line no-console
@@ -153,6 +190,47 @@ export default function Session({ project, resetProject, language, darkMode }) {
     }
   }
 
+  async function endSession() {
+    try {
+      await invoke("end_session");
+    } catch (error) {
+      // eslint-disable-next-line no-console
+      console.error("The session couldn't be ended in the backend.", error);
+    }
+  }
+
+  async function editSessionMetadata() {
+    try {
+      setEditedMetadata(
+        await invoke("get_session_metadata", { sessionId: sessionID }),
+      );
+      metadataDialog.current?.showModal();
+    } catch (error) {
+      // eslint-disable-next-line no-console
+      console.error("The session metadata couldn't be loaded.", error);
+    }
+  }
+
+  async function undoLastStep() {
+    try {
+      const undone = await invoke("undo_last_step");
+      const index = chosenTheme?.questions.findIndex(
+        (q) => q.key === undone?.questionKey,
+      );
+      if (!undone || index === undefined || index === -1) return;
+
+      if (phase !== index + 1 || step !== STEPS.showMainInteractionScreen) {
+        continueVote.current = true;
+      }
+      setPhase(index + 1);
+      setStep(STEPS.showMainInteractionScreen);
+      setTagsMap(undone.tagsMap);
+    } catch (error) {
+      // eslint-disable-next-line no-console
+      console.error("The last step couldn't be undone.", error);
+    }
+  }
+
   const tagCount = useMemo(
     () =>
       Object.values(tagsMap).reduce(
@@ -165,6 +243,13 @@ export default function Session({ project, resetProject, language, darkMode }) {
     [tagsMap],
   );
 
+  useEffect(() => {
+    // Every question of the resumed session was answered already, so only its end is missing
+    if (sessionTheme && !resumedTheme) {
+      endSession();
+    }
+  }, []);
+
   useEffect(() => {
     const unlisten = listen("updated-tags", ({ payload }) =>
       setTagsMap(payload),
@@ -203,7 +288,11 @@ export default function Session({ project, resetProject, language, darkMode }) {
     if (chosenTheme === undefined && phase === 0) {
       throw new Error("A theme should be chosen when going to phase != 0");
     }
-    setPhase(chosenTheme.questions[phase] === undefined ? 0 : phase + 1);
+    const finished = chosenTheme.questions[phase] === undefined;
+    if (finished) {
+      endSession();
+    }
+    setPhase(finished ? 0 : phase + 1);
     setStep(STEPS.showBigQuestion);
   }
 
@@ -308,13 +397,27 @@ export default function Session({ project, resetProject, language, darkMode }) {
   }
 
   useEffect(() => {
-    invoke("reset_tags_map");
+    if (continueVote.current) {
+      continueVote.current = false;
+      return;
+    }
+    // Every step starts with a new vote, so we know how long it took to answer
+    invoke("open_step");
     setTagsMap({});
   }, [step, phase]);
 
   function handleKeyDown(event) {
+    // While editing the metadata, keys are meant for its form
+    if (metadataDialog.current?.open) return;
     if (event.code === "ArrowRight") goToNextStep();
     else if (event.code === "ArrowLeft") goToPreviousStep();
+    else if (
+      event.code === "Backspace" &&
+      window.confirm(translate("undo_last_step_confirm", language))
+    )
+      undoLastStep();
+    else if (event.code === "KeyM" && sessionID !== null)
+      editSessionMetadata();
   }
 
   return (
@@ -346,6 +449,13 @@ export default function Session({ project, resetProject, language, darkMode }) {
         tagCount={tagCount}
         tagsMap={tagsMap}
       />
+      <SessionMetadataDialog
+        metadataDialog={metadataDialog}
+        sessionID={sessionID}
+        metadata={editedMetadata}
+        onSaved={setSessionMetadata}
+        onClose={() => setEditedMetadata(null)}
+      />
     </>
   );
 }
@@ -8,6 +8,9 @@ import ImportCard from "./popups/import_project";
 export function StartScreen({
   setProjectKey,
   setDarkMode,
+  setResumedSession,
+  sessionMetadata,
+  setSessionMetadata,
   toggleFullScreen,
   projects,
 }) {
@@ -62,6 +65,9 @@ export function StartScreen({
       <StartProject
         setProjectKey={setProjectKey}
         setDarkMode={setDarkMode}
+        setResumedSession={setResumedSession}
+        sessionMetadata={sessionMetadata}
+        setSessionMetadata={setSessionMetadata}
         selectedProjectKey={selectedProjectKey}
         startDialog={startDialog}
       />
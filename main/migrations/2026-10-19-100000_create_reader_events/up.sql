@@ -0,0 +1,11 @@
+CREATE TABLE reader_events (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  -- We keep milliseconds, since a lot can happen with a reader in a single second
+  created_at DATETIME DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')) NOT NULL,
+  session_id INTEGER,
+  reader_id TEXT NOT NULL,
+  kind TEXT NOT NULL,
+  message TEXT NOT NULL,
+  FOREIGN KEY(session_id) REFERENCES sessions(id)
+);
+CREATE INDEX reader_events_session_id ON reader_events(session_id);
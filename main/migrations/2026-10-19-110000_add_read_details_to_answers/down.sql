@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE answers DROP COLUMN read_count;
+ALTER TABLE answers DROP COLUMN last_seen_at;
+ALTER TABLE answers DROP COLUMN first_seen_at;
+ALTER TABLE answers DROP COLUMN peak_rssi;
+ALTER TABLE answers DROP COLUMN antenna;
@@ -0,0 +1,6 @@
+-- Answers that were saved before these columns existed keep NULL, since we no longer know their reads
+ALTER TABLE answers ADD COLUMN antenna INTEGER;
+ALTER TABLE answers ADD COLUMN peak_rssi INTEGER;
+ALTER TABLE answers ADD COLUMN first_seen_at DATETIME;
+ALTER TABLE answers ADD COLUMN last_seen_at DATETIME;
+ALTER TABLE answers ADD COLUMN read_count INTEGER;
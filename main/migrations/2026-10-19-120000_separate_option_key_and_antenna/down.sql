@@ -0,0 +1,20 @@
+-- This file should undo anything in `up.sql`
+CREATE TABLE answers_old (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  token_key TEXT NOT NULL,
+  option_key TEXT NOT NULL,
+  step_id INTEGER NOT NULL,
+  antenna INTEGER,
+  peak_rssi INTEGER,
+  first_seen_at DATETIME,
+  last_seen_at DATETIME,
+  read_count INTEGER,
+  FOREIGN KEY(step_id) REFERENCES steps(id)
+);
+
+INSERT INTO answers_old (id, token_key, option_key, step_id, antenna, peak_rssi, first_seen_at, last_seen_at, read_count)
+SELECT id, token_key, COALESCE(CAST(antenna AS TEXT), ''), step_id, antenna, peak_rssi, first_seen_at, last_seen_at, read_count
+FROM answers;
+
+DROP TABLE answers;
+ALTER TABLE answers_old RENAME TO answers;
@@ -0,0 +1,34 @@
+-- `option_key` used to hold the antenna of the answer. We move it to `antenna`, and keep `option_key` for the key of
+-- the chosen option. SQLite can't make a column nullable, so we rebuild the table.
+-- The option keys of existing answers are filled in by the application, since it knows the options of every question.
+CREATE TABLE answers_new (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  token_key TEXT NOT NULL,
+  option_key TEXT,
+  step_id INTEGER NOT NULL,
+  antenna INTEGER,
+  peak_rssi INTEGER,
+  first_seen_at DATETIME,
+  last_seen_at DATETIME,
+  read_count INTEGER,
+  FOREIGN KEY(step_id) REFERENCES steps(id)
+);
+
+INSERT INTO answers_new (id, token_key, option_key, step_id, antenna, peak_rssi, first_seen_at, last_seen_at, read_count)
+SELECT
+  id,
+  token_key,
+  NULL,
+  step_id,
+  COALESCE(
+    antenna,
+    CASE WHEN option_key <> '' AND option_key NOT GLOB '*[^0-9]*' THEN CAST(option_key AS INTEGER) END
+  ),
+  peak_rssi,
+  first_seen_at,
+  last_seen_at,
+  read_count
+FROM answers;
+
+DROP TABLE answers;
+ALTER TABLE answers_new RENAME TO answers;
@@ -0,0 +1,10 @@
+-- Every version of a project that was used in a session, by the SHA-256 hash of its definition
+CREATE TABLE project_snapshots (
+  hash TEXT PRIMARY KEY NOT NULL,
+  created_at DATETIME DEFAULT current_timestamp NOT NULL,
+  project_key TEXT NOT NULL,
+  content TEXT NOT NULL
+);
+
+-- Sessions that started before we kept snapshots have no hash
+ALTER TABLE sessions ADD COLUMN project_hash TEXT REFERENCES project_snapshots(hash);
@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE answers DROP COLUMN option_id;
+ALTER TABLE steps DROP COLUMN question_id;
+ALTER TABLE sessions DROP COLUMN theme_id;
+DROP TABLE options;
+DROP TABLE questions;
+DROP TABLE themes;
+DROP TABLE projects;
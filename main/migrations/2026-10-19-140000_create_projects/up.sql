@@ -0,0 +1,49 @@
+-- The projects, themes, questions and options are seeded from the project files on startup.
+-- Translated properties are stored as JSON objects, with a key per language.
+-- Rows that are removed from a project are archived instead of deleted, since answers may still refer to them.
+CREATE TABLE projects (
+  key TEXT PRIMARY KEY NOT NULL,
+  name TEXT NOT NULL,
+  available_languages TEXT NOT NULL
+);
+
+CREATE TABLE themes (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  project_key TEXT NOT NULL,
+  key TEXT NOT NULL,
+  name TEXT NOT NULL,
+  position INTEGER NOT NULL,
+  archived BOOLEAN DEFAULT 0 NOT NULL,
+  FOREIGN KEY(project_key) REFERENCES projects(key),
+  UNIQUE(project_key, key)
+);
+
+CREATE TABLE questions (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  theme_id INTEGER NOT NULL,
+  key TEXT NOT NULL,
+  title TEXT NOT NULL,
+  explanation TEXT,
+  question_type TEXT,
+  position INTEGER NOT NULL,
+  archived BOOLEAN DEFAULT 0 NOT NULL,
+  FOREIGN KEY(theme_id) REFERENCES themes(id),
+  UNIQUE(theme_id, key)
+);
+
+CREATE TABLE options (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  question_id INTEGER NOT NULL,
+  key TEXT NOT NULL,
+  value TEXT NOT NULL,
+  correct BOOLEAN,
+  position INTEGER NOT NULL,
+  archived BOOLEAN DEFAULT 0 NOT NULL,
+  FOREIGN KEY(question_id) REFERENCES questions(id),
+  UNIQUE(question_id, key)
+);
+
+-- These are linked to the rows above once they are seeded
+ALTER TABLE sessions ADD COLUMN theme_id INTEGER REFERENCES themes(id);
+ALTER TABLE steps ADD COLUMN question_id INTEGER REFERENCES questions(id);
+ALTER TABLE answers ADD COLUMN option_id INTEGER REFERENCES options(id);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE sessions DROP COLUMN status;
+ALTER TABLE sessions DROP COLUMN ended_at;
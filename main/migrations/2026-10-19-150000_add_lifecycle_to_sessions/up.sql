@@ -0,0 +1,5 @@
+-- Sessions are in progress until they are ended, either after answering every question (`Completed`) or not (`Abandoned`)
+ALTER TABLE sessions ADD COLUMN ended_at DATETIME;
+ALTER TABLE sessions ADD COLUMN status TEXT DEFAULT 'InProgress' NOT NULL;
+-- We don't know how or when earlier sessions ended, so we don't pretend to
+UPDATE sessions SET status = 'Unknown';
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE answers DROP COLUMN time_to_vote_ms;
+ALTER TABLE steps DROP COLUMN closed_at;
+ALTER TABLE steps DROP COLUMN opened_at;
@@ -0,0 +1,5 @@
+-- When voting on a step opened and closed. Steps that were saved before we kept these have neither.
+ALTER TABLE steps ADD COLUMN opened_at DATETIME;
+ALTER TABLE steps ADD COLUMN closed_at DATETIME;
+-- The time between opening the vote and first seeing the token, in milliseconds
+ALTER TABLE answers ADD COLUMN time_to_vote_ms INTEGER;
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+-- NOTE: Steps that became revisions are not restored
+DROP INDEX steps_session_id_question_key;
+DROP TABLE step_revisions;
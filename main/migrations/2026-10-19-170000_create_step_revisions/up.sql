@@ -0,0 +1,52 @@
+-- The versions of a step that were replaced by recording the step again, with their answers as JSON
+CREATE TABLE step_revisions (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  step_id INTEGER NOT NULL,
+  superseded_at DATETIME DEFAULT current_timestamp NOT NULL,
+  opened_at DATETIME,
+  closed_at DATETIME NOT NULL,
+  answers TEXT NOT NULL,
+  FOREIGN KEY(step_id) REFERENCES steps(id)
+);
+
+-- Questions that were saved more than once in a session keep their last step.
+-- The earlier ones become revisions of it.
+CREATE TEMPORARY TABLE superseded_steps AS
+SELECT old.id AS id, latest.id AS latest_id
+FROM steps old
+INNER JOIN steps latest ON latest.id = (
+  SELECT MAX(s.id) FROM steps s
+  WHERE s.session_id = old.session_id AND s.question_key = old.question_key
+)
+WHERE old.id <> latest.id;
+
+INSERT INTO step_revisions (step_id, opened_at, closed_at, answers)
+SELECT
+  superseded_steps.latest_id,
+  steps.opened_at,
+  COALESCE(steps.closed_at, steps.created_at),
+  (
+    SELECT json_group_array(json_object(
+      'id', answers.id,
+      'step_id', answers.step_id,
+      'option_key', answers.option_key,
+      'token_key', answers.token_key,
+      'antenna', answers.antenna,
+      'peak_rssi', answers.peak_rssi,
+      'first_seen_at', answers.first_seen_at,
+      'last_seen_at', answers.last_seen_at,
+      'read_count', answers.read_count,
+      'option_id', answers.option_id,
+      'time_to_vote_ms', answers.time_to_vote_ms
+    ))
+    FROM answers WHERE answers.step_id = steps.id
+  )
+FROM superseded_steps
+INNER JOIN steps ON steps.id = superseded_steps.id
+ORDER BY steps.id;
+
+DELETE FROM answers WHERE step_id IN (SELECT id FROM superseded_steps);
+DELETE FROM steps WHERE id IN (SELECT id FROM superseded_steps);
+DROP TABLE superseded_steps;
+
+CREATE UNIQUE INDEX steps_session_id_question_key ON steps(session_id, question_key);
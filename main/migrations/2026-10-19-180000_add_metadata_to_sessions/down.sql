@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE sessions DROP COLUMN notes;
+ALTER TABLE sessions DROP COLUMN expected_participants;
+ALTER TABLE sessions DROP COLUMN age_range;
+ALTER TABLE sessions DROP COLUMN group_name;
+ALTER TABLE sessions DROP COLUMN facilitator;
+ALTER TABLE sessions DROP COLUMN location;
@@ -0,0 +1,7 @@
+-- Context about a session for our analysts. Every field is optional and can be edited after the session.
+ALTER TABLE sessions ADD COLUMN location TEXT;
+ALTER TABLE sessions ADD COLUMN facilitator TEXT;
+ALTER TABLE sessions ADD COLUMN group_name TEXT;
+ALTER TABLE sessions ADD COLUMN age_range TEXT;
+ALTER TABLE sessions ADD COLUMN expected_participants INTEGER;
+ALTER TABLE sessions ADD COLUMN notes TEXT;
@@ -0,0 +1,2 @@
+-- Undoing a step keeps it and its revisions, so we only mark when it was undone
+ALTER TABLE steps ADD COLUMN voided_at DATETIME;
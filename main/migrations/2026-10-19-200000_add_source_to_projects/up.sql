@@ -0,0 +1,2 @@
+-- The contents of the project file, so snapshots include the properties we don't map
+ALTER TABLE projects ADD COLUMN source TEXT;
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE answers DROP COLUMN option_key_backfilled;
@@ -0,0 +1,3 @@
+-- Answers whose option key was resolved afterwards from their antenna, instead of while voting
+-- NOTE: Databases that were backfilled before this column existed can no longer tell which answers were resolved
+ALTER TABLE answers ADD COLUMN option_key_backfilled BOOLEAN DEFAULT 0 NOT NULL;
@@ -0,0 +1,2 @@
+-- Projects whose file was removed are archived, since sessions may still refer to them
+ALTER TABLE projects ADD COLUMN archived BOOLEAN DEFAULT 0 NOT NULL;
@@ -2,20 +2,34 @@ pub mod models;
 pub mod schema;
 
 use crate::{
-    database::models::{Answer, Session, Step},
-    tags::TagsMap,
+    database::models::{
+        Answer, OptionRecord, ProjectRecord, QuestionRecord, ReaderLogEntry, Session,
+        SessionMetadata, SessionStatus, Step, StepRevision, ThemeRecord,
+    },
+    projects::{Project, Question, QuestionOption, Theme, TranslatedProperty},
+    tags::{Tag, TagsMap},
 };
 use diesel::{prelude::*, sqlite::Sqlite};
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
-use std::{error::Error, path};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    path,
+};
 
 // NOTE: This path is relative to our root, and not this file.
 const MIGRATIONS: EmbeddedMigrations = embed_migrations!("./migrations");
+/// The setting that tells us the option keys of old answers were filled in
+const OPTION_KEYS_BACKFILLED_KEY: &str = "answers_option_keys_backfilled";
 
 pub fn setup_database(location: &path::PathBuf) -> Result<SqliteConnection, Box<dyn Error>> {
     let mut connection = establish_connection(&location);
     run_migrations(&mut connection)
         .unwrap_or_else(|err| panic!("Could not run migrations, due to {}", err));
+    seed_projects(&mut connection, &Project::build_all())?;
+    backfill_option_keys(&mut connection)?;
+    link_to_projects(&mut connection)?;
 
     Ok(connection)
 }
@@ -24,34 +38,475 @@ pub fn create_session(
     connection: &mut SqliteConnection,
     project_key: &str,
     theme_key: &str,
+    project_hash: Option<&str>,
+    metadata: &SessionMetadata,
 ) -> Session {
-    use crate::database::schema::sessions;
+    use crate::database::schema::{sessions, themes};
+
+    let theme_id: Option<i32> = themes::table
+        .filter(themes::project_key.eq(project_key))
+        .filter(themes::key.eq(theme_key))
+        .select(themes::id)
+        .first(connection)
+        .ok();
 
     diesel::insert_into(sessions::table)
         .values((
             sessions::project_key.eq(project_key),
             sessions::theme_key.eq(theme_key),
+            sessions::project_hash.eq(project_hash),
+            sessions::theme_id.eq(theme_id),
+            metadata,
         ))
         .returning(Session::as_returning())
         .get_result(connection)
         .expect("Error saving new session")
 }
 
+/// Replace the metadata of a session, which can be done at any time
+pub fn update_session_metadata(
+    connection: &mut SqliteConnection,
+    session_id: &i32,
+    metadata: &SessionMetadata,
+) -> Result<Session, String> {
+    use crate::database::schema::sessions;
+
+    diesel::update(sessions::table.find(session_id))
+        .set(metadata)
+        .returning(Session::as_returning())
+        .get_result(connection)
+        .map_err(|err| err.to_string())
+}
+
+pub fn get_session_metadata(
+    connection: &mut SqliteConnection,
+    session_id: &i32,
+) -> Result<SessionMetadata, String> {
+    use crate::database::schema::sessions;
+
+    sessions::table
+        .find(session_id)
+        .select(SessionMetadata::as_select())
+        .first(connection)
+        .map_err(|err| err.to_string())
+}
+
+/// End a session that is still in progress
+///
+/// A session can only be ended once, so its end time and status never change afterwards.
+pub fn end_session(
+    connection: &mut SqliteConnection,
+    session_id: &i32,
+    status: SessionStatus,
+) -> Result<Session, String> {
+    use crate::database::schema::sessions;
+
+    diesel::update(
+        sessions::table
+            .find(session_id)
+            .filter(sessions::status.eq(SessionStatus::InProgress)),
+    )
+    .set((
+        sessions::ended_at.eq(diesel::dsl::now),
+        sessions::status.eq(status),
+    ))
+    .returning(Session::as_returning())
+    .get_result(connection)
+    .optional()
+    .map_err(|err| err.to_string())?
+    .ok_or_else(|| format!("Session {} is not in progress", session_id))
+}
+
+/// Mark every session that is still in progress as abandoned, returning how many there were
+pub fn abandon_sessions(connection: &mut SqliteConnection) -> Result<usize, String> {
+    use crate::database::schema::sessions;
+
+    diesel::update(sessions::table.filter(sessions::status.eq(SessionStatus::InProgress)))
+        .set((
+            sessions::ended_at.eq(diesel::dsl::now),
+            sessions::status.eq(SessionStatus::Abandoned),
+        ))
+        .execute(connection)
+        .map_err(|err| err.to_string())
+}
+
+/// The keys of the questions that have a step in this session
+pub fn get_answered_questions(
+    connection: &mut SqliteConnection,
+    session_id: &i32,
+) -> Result<HashSet<String>, String> {
+    use crate::database::schema::steps;
+
+    steps::table
+        .filter(steps::session_id.eq(session_id))
+        .filter(steps::voided_at.is_null())
+        .select(steps::question_key)
+        .load::<String>(connection)
+        .map(|keys| keys.into_iter().collect())
+        .map_err(|err| err.to_string())
+}
+
+/// The most recent session that was never ended, e.g. because the app was closed or crashed during it
+pub fn get_unfinished_session(
+    connection: &mut SqliteConnection,
+) -> Result<Option<Session>, String> {
+    use crate::database::schema::sessions;
+
+    sessions::table
+        .filter(sessions::status.eq(SessionStatus::InProgress))
+        .order(sessions::id.desc())
+        .select(Session::as_select())
+        .first(connection)
+        .optional()
+        .map_err(|err| err.to_string())
+}
+
+/// The key of the question that was answered last in this session
+pub fn get_last_step(
+    connection: &mut SqliteConnection,
+    session_id: &i32,
+) -> Result<Option<String>, String> {
+    use crate::database::schema::steps;
+
+    steps::table
+        .filter(steps::session_id.eq(session_id))
+        .filter(steps::voided_at.is_null())
+        .order(steps::id.desc())
+        .select(steps::question_key)
+        .first(connection)
+        .optional()
+        .map_err(|err| err.to_string())
+}
+
+/// Keep the current definition of a project, returning the hash of its snapshot
+///
+/// A snapshot is only stored once, no matter how many sessions use it.
+pub fn save_project_snapshot(
+    connection: &mut SqliteConnection,
+    project: &Project,
+) -> Result<String, String> {
+    use crate::database::schema::project_snapshots;
+
+    let (hash, content) = project.snapshot();
+    diesel::insert_or_ignore_into(project_snapshots::table)
+        .values((
+            project_snapshots::hash.eq(&hash),
+            project_snapshots::project_key.eq(&project.key),
+            project_snapshots::content.eq(content),
+        ))
+        .execute(connection)
+        .map_err(|err| err.to_string())?;
+    Ok(hash)
+}
+
+/// The project as it was when its snapshot was taken
+pub fn get_project_snapshot(connection: &mut SqliteConnection, hash: &str) -> Option<Project> {
+    use crate::database::schema::project_snapshots;
+
+    let content: String = project_snapshots::table
+        .find(hash)
+        .select(project_snapshots::content)
+        .first(connection)
+        .ok()?;
+    Project::parse(&content).ok()
+}
+
+/// Store the definition of the projects, so sessions, steps and answers can refer to it
+///
+/// Themes, questions and options that already exist are updated in place, so they keep their id.
+/// The ones that were removed from a project are archived, since answers may still refer to them.
+/// The same goes for projects whose file was removed.
+pub fn seed_projects(
+    connection: &mut SqliteConnection,
+    projects: &[Project],
+) -> Result<(), String> {
+    use crate::database::schema::projects as projects_table;
+
+    connection
+        .transaction::<_, diesel::result::Error, _>(|connection| {
+            diesel::update(
+                projects_table::table.filter(
+                    projects_table::key.ne_all(projects.iter().map(|project| &project.key)),
+                ),
+            )
+            .set(projects_table::archived.eq(true))
+            .execute(connection)?;
+            for project in projects {
+                seed_project(connection, project)?;
+            }
+            Ok(())
+        })
+        .map_err(|err| err.to_string())
+}
+
+fn seed_project(connection: &mut SqliteConnection, project: &Project) -> QueryResult<()> {
+    use crate::database::schema::{options, projects, questions, themes};
+
+    let name = to_json(&project.name);
+    let languages = to_json(&project.available_languages);
+    diesel::insert_into(projects::table)
+        .values((
+            projects::key.eq(&project.key),
+            projects::name.eq(&name),
+            projects::available_languages.eq(&languages),
+            projects::source.eq(&project.source),
+        ))
+        .on_conflict(projects::key)
+        .do_update()
+        .set((
+            projects::name.eq(&name),
+            projects::available_languages.eq(&languages),
+            projects::source.eq(&project.source),
+            projects::archived.eq(false),
+        ))
+        .execute(connection)?;
+
+    // Everything we still find in the project is restored below
+    let theme_ids = themes::table
+        .filter(themes::project_key.eq(&project.key))
+        .select(themes::id);
+    let question_ids = questions::table
+        .filter(questions::theme_id.eq_any(theme_ids.clone()))
+        .select(questions::id);
+    diesel::update(options::table.filter(options::question_id.eq_any(question_ids)))
+        .set(options::archived.eq(true))
+        .execute(connection)?;
+    diesel::update(questions::table.filter(questions::theme_id.eq_any(theme_ids)))
+        .set(questions::archived.eq(true))
+        .execute(connection)?;
+    diesel::update(themes::table.filter(themes::project_key.eq(&project.key)))
+        .set(themes::archived.eq(true))
+        .execute(connection)?;
+
+    for (position, theme) in (0..).zip(&project.themes) {
+        let name = to_json(&theme.name);
+        let theme_id: i32 = diesel::insert_into(themes::table)
+            .values((
+                themes::project_key.eq(&project.key),
+                themes::key.eq(&theme.key),
+                themes::name.eq(&name),
+                themes::position.eq(position),
+            ))
+            .on_conflict((themes::project_key, themes::key))
+            .do_update()
+            .set((
+                themes::name.eq(&name),
+                themes::position.eq(position),
+                themes::archived.eq(false),
+            ))
+            .returning(themes::id)
+            .get_result(connection)?;
+
+        for (position, question) in (0..).zip(&theme.questions) {
+            let title = to_json(&question.title);
+            let explanation = question.explanation.as_ref().map(to_json);
+            let question_id: i32 = diesel::insert_into(questions::table)
+                .values((
+                    questions::theme_id.eq(theme_id),
+                    questions::key.eq(&question.key),
+                    questions::title.eq(&title),
+                    questions::explanation.eq(&explanation),
+                    questions::question_type.eq(&question.r#type),
+                    questions::position.eq(position),
+                ))
+                .on_conflict((questions::theme_id, questions::key))
+                .do_update()
+                .set((
+                    questions::title.eq(&title),
+                    questions::explanation.eq(&explanation),
+                    questions::question_type.eq(&question.r#type),
+                    questions::position.eq(position),
+                    questions::archived.eq(false),
+                ))
+                .returning(questions::id)
+                .get_result(connection)?;
+
+            for (position, option) in (0..).zip(question.options.iter().flatten()) {
+                let value = to_json(&option.value);
+                diesel::insert_into(options::table)
+                    .values((
+                        options::question_id.eq(question_id),
+                        options::key.eq(&option.key),
+                        options::value.eq(&value),
+                        options::correct.eq(option.correct),
+                        options::position.eq(position),
+                    ))
+                    .on_conflict((options::question_id, options::key))
+                    .do_update()
+                    .set((
+                        options::value.eq(&value),
+                        options::correct.eq(option.correct),
+                        options::position.eq(position),
+                        options::archived.eq(false),
+                    ))
+                    .execute(connection)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Link sessions, steps and answers that were saved before we stored the projects to their theme, question and option
+///
+/// This only touches rows that aren't linked yet. Rows whose keys don't exist (anymore) stay unlinked.
+pub fn link_to_projects(connection: &mut SqliteConnection) -> Result<(), String> {
+    let queries = [
+        "UPDATE sessions SET theme_id = (
+            SELECT themes.id FROM themes
+            WHERE themes.project_key = sessions.project_key AND themes.key = sessions.theme_key
+        ) WHERE theme_id IS NULL",
+        "UPDATE steps SET question_id = (
+            SELECT questions.id FROM questions
+            INNER JOIN sessions ON sessions.theme_id = questions.theme_id
+            WHERE sessions.id = steps.session_id AND questions.key = steps.question_key
+        ) WHERE question_id IS NULL",
+        "UPDATE answers SET option_id = (
+            SELECT options.id FROM options
+            INNER JOIN steps ON steps.question_id = options.question_id
+            WHERE steps.id = answers.step_id AND options.key = answers.option_key
+        ) WHERE option_id IS NULL AND option_key IS NOT NULL",
+    ];
+
+    for query in queries {
+        diesel::sql_query(query)
+            .execute(connection)
+            .map_err(|err| err.to_string())?;
+    }
+    Ok(())
+}
+
+/// The current definition of a project, without the themes, questions and options that were archived
+///
+/// Archived projects are still returned, so the sessions that refer to them can be exported.
+pub fn get_project(connection: &mut SqliteConnection, key: &str) -> Option<Project> {
+    use crate::database::schema::projects;
+
+    let record = projects::table
+        .find(key)
+        .select(ProjectRecord::as_select())
+        .first(connection)
+        .ok()?;
+    load_project(connection, record).ok()
+}
+
+/// All projects that are not archived, ordered by their key
+pub fn get_projects(connection: &mut SqliteConnection) -> Result<Vec<Project>, String> {
+    use crate::database::schema::projects;
+
+    let records = projects::table
+        .filter(projects::archived.eq(false))
+        .order(projects::key.asc())
+        .select(ProjectRecord::as_select())
+        .load(connection)
+        .map_err(|err| err.to_string())?;
+    records
+        .into_iter()
+        .map(|record| load_project(connection, record).map_err(|err| err.to_string()))
+        .collect()
+}
+
+fn load_project(connection: &mut SqliteConnection, record: ProjectRecord) -> QueryResult<Project> {
+    use crate::database::schema::{options, questions, themes};
+
+    let theme_records: Vec<ThemeRecord> = themes::table
+        .filter(themes::project_key.eq(&record.key))
+        .filter(themes::archived.eq(false))
+        .order(themes::position.asc())
+        .select(ThemeRecord::as_select())
+        .load(connection)?;
+    let question_records: Vec<QuestionRecord> = questions::table
+        .filter(questions::theme_id.eq_any(theme_records.iter().map(|theme| theme.id)))
+        .filter(questions::archived.eq(false))
+        .order(questions::position.asc())
+        .select(QuestionRecord::as_select())
+        .load(connection)?;
+    let option_records: Vec<OptionRecord> = options::table
+        .filter(options::question_id.eq_any(question_records.iter().map(|question| question.id)))
+        .filter(options::archived.eq(false))
+        .order(options::position.asc())
+        .select(OptionRecord::as_select())
+        .load(connection)?;
+
+    let themes = theme_records
+        .into_iter()
+        .map(|theme| Theme {
+            questions: question_records
+                .iter()
+                .filter(|question| question.theme_id == theme.id)
+                .map(|question| {
+                    let options: Vec<QuestionOption> = option_records
+                        .iter()
+                        .filter(|option| option.question_id == question.id)
+                        .map(|option| QuestionOption {
+                            key: option.key.clone(),
+                            value: from_json(&option.value),
+                            correct: option.correct,
+                        })
+                        .collect();
+                    Question {
+                        title: from_json(&question.title),
+                        explanation: question.explanation.as_deref().map(from_json),
+                        r#type: question.question_type.clone(),
+                        key: question.key.clone(),
+                        // Questions without options (e.g. an explanation) have none in their project file either
+                        options: (!options.is_empty()).then_some(options),
+                    }
+                })
+                .collect(),
+            key: theme.key,
+            name: from_json(&theme.name),
+        })
+        .collect();
+
+    Ok(Project {
+        key: record.key,
+        themes,
+        name: from_json(&record.name),
+        available_languages: serde_json::from_str(&record.available_languages).unwrap_or_default(),
+        source: record.source,
+    })
+}
+
+fn to_json<T: Serialize>(value: &T) -> String {
+    // We can unwrap, since we only store strings, lists and structs
+    serde_json::to_string(value).unwrap()
+}
+
+/// Read a translated property, falling back to one without translations if it can't be parsed
+fn from_json(value: &str) -> TranslatedProperty {
+    serde_json::from_str(value).unwrap_or_default()
+}
+
 pub fn create_step(
     connection: &mut SqliteConnection,
     session_id: &i32,
     question_key: &str,
-) -> Step {
-    use crate::database::schema::steps;
+) -> QueryResult<Step> {
+    use crate::database::schema::{questions, sessions, steps};
 
+    let question_id: Option<i32> = questions::table
+        .inner_join(sessions::table.on(sessions::theme_id.eq(questions::theme_id.nullable())))
+        .filter(sessions::id.eq(session_id))
+        .filter(questions::key.eq(question_key))
+        .select(questions::id)
+        .first(connection)
+        .optional()?;
+
+    // A question has one step per session, so we reuse the step if it already exists
     diesel::insert_into(steps::table)
         .values((
             steps::session_id.eq(session_id),
             steps::question_key.eq(question_key),
+            steps::question_id.eq(question_id),
         ))
-        .returning(Step::as_returning())
-        .get_result(connection)
-        .expect("Error saving step")
+        .on_conflict((steps::session_id, steps::question_key))
+        .do_nothing()
+        .execute(connection)?;
+    steps::table
+        .filter(steps::session_id.eq(session_id))
+        .filter(steps::question_key.eq(question_key))
+        .select(Step::as_select())
+        .first(connection)
 }
 
 pub fn create_answer(
@@ -73,31 +528,418 @@ pub fn create_answer(
         .expect("Error saving answer")
 }
 
+/// When voting on a step opened and closed, in microseconds since the unix epoch
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StepWindow {
+    pub opened_at: u64,
+    pub closed_at: u64,
+}
+
+impl StepWindow {
+    /// The time between opening the vote and first receiving a token, in milliseconds
+    ///
+    /// Both times come from our own clock, since the clock of a reader can differ from ours.
+    /// Tokens that were already received before the vote opened voted right away.
+    pub fn time_to_vote_ms(&self, received_at: u64) -> i32 {
+        i32::try_from(received_at.saturating_sub(self.opened_at) / 1000).unwrap_or(i32::MAX)
+    }
+}
+
+/// Save an answer for every token in the `TagsMap`
+///
+/// The antenna of every token is resolved to the key of the matching option of `question` right away,
+/// so changing the options of a question afterwards never changes historical answers.
+///
+/// Every question has one step per session. If this step was already saved, its answers are replaced
+/// and the ones we replace are kept as a revision of the step, so we can always see what was superseded.
 pub fn save_step_results(
     connection: &mut SqliteConnection,
     session_id: &i32,
     current_step: &str,
+    question: Option<&Question>,
+    window: Option<StepWindow>,
     tags_map: TagsMap,
 ) -> Result<(Step, usize), String> {
-    use crate::database::schema::answers;
+    connection
+        .transaction::<_, diesel::result::Error, _>(|connection| {
+            let step = match find_step(connection, session_id, current_step)? {
+                Some(step) => {
+                    // The answers of a voided step were superseded when it was undone
+                    if step.voided_at.is_none() {
+                        supersede_answers(connection, &step)?;
+                    }
+                    step
+                }
+                None => create_step(connection, session_id, current_step)?,
+            };
+            save_answers(connection, step, question, window, &tags_map)
+        })
+        .map_err(|err| err.to_string())
+}
+
+/// Replace the answers of a step that was saved before, e.g. when a question is asked again
+///
+/// This is the same as `save_step_results`, except that it fails when the step was never saved.
+pub fn redo_step(
+    connection: &mut SqliteConnection,
+    session_id: &i32,
+    current_step: &str,
+    question: Option<&Question>,
+    window: Option<StepWindow>,
+    tags_map: TagsMap,
+) -> Result<(Step, usize), String> {
+    if find_step(connection, session_id, current_step)
+        .map_err(|err| err.to_string())?
+        .is_none()
+    {
+        return Err(format!(
+            "Step {} was never saved in session {}",
+            current_step, session_id
+        ));
+    }
+    save_step_results(
+        connection,
+        session_id,
+        current_step,
+        question,
+        window,
+        tags_map,
+    )
+}
+
+/// Move the answers of a step into a new revision of it
+fn supersede_answers(connection: &mut SqliteConnection, step: &Step) -> QueryResult<()> {
+    use crate::database::schema::{answers, step_revisions};
+
+    let superseded: Vec<Answer> = answers::table
+        .filter(answers::step_id.eq(step.id))
+        .order(answers::id.asc())
+        .select(Answer::as_select())
+        .load(connection)?;
+    diesel::insert_into(step_revisions::table)
+        .values((
+            step_revisions::step_id.eq(step.id),
+            step_revisions::opened_at.eq(step.opened_at),
+            step_revisions::closed_at.eq(step.closed_at.unwrap_or(step.created_at)),
+            // We can unwrap, since answers only consist of strings, numbers and timestamps
+            step_revisions::answers.eq(serde_json::to_string(&superseded).unwrap()),
+        ))
+        .execute(connection)?;
+    diesel::delete(answers::table.filter(answers::step_id.eq(step.id))).execute(connection)?;
+    Ok(())
+}
+
+/// Undo the step that was saved last in a session
+///
+/// The step is kept, but marked as voided until it is saved again. Its answers are moved into a revision,
+/// so undoing never loses what was saved. We return the step and its answers as a `TagsMap`,
+/// so voting on it can continue where it stopped.
+pub fn undo_last_step(
+    connection: &mut SqliteConnection,
+    session_id: &i32,
+) -> Result<Option<(Step, TagsMap)>, String> {
+    use crate::database::schema::{answers, steps};
+
+    connection
+        .transaction::<_, diesel::result::Error, _>(|connection| {
+            let Some(step) = steps::table
+                .filter(steps::session_id.eq(session_id))
+                .filter(steps::voided_at.is_null())
+                .order(steps::id.desc())
+                .select(Step::as_select())
+                .first(connection)
+                .optional()?
+            else {
+                return Ok(None);
+            };
+
+            let answers: Vec<Answer> = answers::table
+                .filter(answers::step_id.eq(step.id))
+                .select(Answer::as_select())
+                .load(connection)?;
+            supersede_answers(connection, &step)?;
+            diesel::update(steps::table.find(step.id))
+                .set(steps::voided_at.eq(diesel::dsl::now))
+                .execute(connection)?;
+
+            let mut tags_map = TagsMap::new();
+            let opened_at = step.opened_at.map(datetime_to_micros);
+            // Answers that were saved before we kept their antenna can't be restored
+            for answer in answers.iter().filter(|answer| answer.antenna.is_some()) {
+                tags_map.add_tag(answer_to_tag(answer, opened_at));
+            }
+            Ok(Some((step, tags_map)))
+        })
+        .map_err(|err| err.to_string())
+}
+
+/// Restore the token of an answer, as it was when the step of the answer was saved
+///
+/// We only know when the token was received if the vote on its step was opened explicitly,
+/// otherwise we use when the reader first saw it.
+fn answer_to_tag(answer: &Answer, opened_at: Option<u64>) -> Tag {
+    let strength = answer
+        .peak_rssi
+        .and_then(|rssi| i8::try_from(rssi).ok())
+        .unwrap_or_default();
+    let mut tag = Tag::new(
+        answer.token_key.clone(),
+        answer
+            .antenna
+            .and_then(|antenna| u16::try_from(antenna).ok())
+            .unwrap_or_default(),
+        strength,
+    );
+    if let Some(first_seen) = answer.first_seen_at.map(datetime_to_micros) {
+        tag.first_seen = first_seen;
+    }
+    if let Some(last_seen) = answer.last_seen_at.map(datetime_to_micros) {
+        tag.last_seen = last_seen;
+    }
+    if let Some(read_count) = answer.read_count {
+        tag.read_count = u32::try_from(read_count).unwrap_or_default();
+    }
+    tag.received_at = match (opened_at, answer.time_to_vote_ms) {
+        (Some(opened_at), Some(time_to_vote_ms)) => {
+            opened_at + u64::try_from(time_to_vote_ms).unwrap_or_default() * 1000
+        }
+        _ => tag.first_seen,
+    };
+    tag
+}
+
+/// The step of a question in a session, if it was saved
+pub fn find_step(
+    connection: &mut SqliteConnection,
+    session_id: &i32,
+    question_key: &str,
+) -> QueryResult<Option<Step>> {
+    use crate::database::schema::steps;
+
+    steps::table
+        .filter(steps::session_id.eq(session_id))
+        .filter(steps::question_key.eq(question_key))
+        .select(Step::as_select())
+        .first(connection)
+        .optional()
+}
+
+/// The superseded versions of a step, oldest first
+pub fn get_step_revisions(
+    connection: &mut SqliteConnection,
+    step_id: &i32,
+) -> Result<Vec<StepRevision>, String> {
+    use crate::database::schema::step_revisions;
 
-    let step = create_step(connection, session_id, current_step);
+    step_revisions::table
+        .filter(step_revisions::step_id.eq(step_id))
+        .order(step_revisions::id.asc())
+        .select(StepRevision::as_select())
+        .load(connection)
+        .map_err(|err| err.to_string())
+}
+
+/// Save the voting window of a step and an answer for every token
+fn save_answers(
+    connection: &mut SqliteConnection,
+    step: Step,
+    question: Option<&Question>,
+    window: Option<StepWindow>,
+    tags_map: &TagsMap,
+) -> QueryResult<(Step, usize)> {
+    use crate::database::schema::{answers, options, steps};
+
+    // A step saved without a window never keeps the window of an earlier save
+    let step = diesel::update(steps::table.find(step.id))
+        .set((
+            steps::voided_at.eq(None::<time::PrimitiveDateTime>),
+            steps::opened_at.eq(window.and_then(|window| micros_to_datetime(window.opened_at))),
+            steps::closed_at.eq(window.and_then(|window| micros_to_datetime(window.closed_at))),
+        ))
+        .returning(Step::as_returning())
+        .get_result(connection)?;
+
+    let option_ids: HashMap<String, i32> = match step.question_id {
+        Some(question_id) => options::table
+            .filter(options::question_id.eq(question_id))
+            .select((options::key, options::id))
+            .load(connection)?
+            .into_iter()
+            .collect(),
+        None => HashMap::new(),
+    };
     let records: Vec<_> = tags_map
         .values()
         .map(|tag| {
+            let option_key = question
+                .and_then(|q| q.find_option_by_antenna_index(tag.antenna.into()))
+                .map(|option| option.key);
+            let option_id = option_key
+                .as_ref()
+                .and_then(|key| option_ids.get(key).copied());
             (
                 answers::step_id.eq(step.id),
-                answers::option_key.eq(tag.antenna.to_string()),
+                answers::option_key.eq(option_key),
+                answers::option_id.eq(option_id),
                 answers::token_key.eq(&tag.id),
+                answers::antenna.eq(i32::from(tag.antenna)),
+                answers::peak_rssi.eq(i32::from(tag.peak_strength)),
+                answers::first_seen_at.eq(micros_to_datetime(tag.first_seen)),
+                answers::last_seen_at.eq(micros_to_datetime(tag.last_seen)),
+                answers::read_count.eq(i32::try_from(tag.read_count).unwrap_or(i32::MAX)),
+                answers::time_to_vote_ms
+                    .eq(window.map(|window| window.time_to_vote_ms(tag.received_at))),
             )
         })
         .collect();
 
-    match diesel::insert_into(answers::table)
+    let answers = diesel::insert_into(answers::table)
         .values(records)
+        .execute(connection)?;
+    Ok((step, answers))
+}
+
+/// Fill in the option key of answers that were saved when we only stored their antenna
+///
+/// This only runs once, right after the migration that separated both. The antennas are resolved against the
+/// current definition of the project, so if its options were reordered or relabeled since the event, the option key
+/// can be wrong. We therefore flag every answer we fill in, so analysts can tell them apart.
+/// Answers that can't be resolved keep no option key.
+pub fn backfill_option_keys(connection: &mut SqliteConnection) -> Result<usize, String> {
+    use crate::database::schema::{answers, sessions, steps};
+
+    if get_setting::<bool>(connection, OPTION_KEYS_BACKFILLED_KEY).unwrap_or(false) {
+        return Ok(0);
+    }
+
+    let unresolved: Vec<(i32, i32, String, String, String)> = answers::table
+        .inner_join(steps::table.inner_join(sessions::table))
+        .filter(answers::option_key.is_null())
+        .filter(answers::antenna.is_not_null())
+        .select((
+            answers::id,
+            answers::antenna.assume_not_null(),
+            sessions::project_key,
+            sessions::theme_key,
+            steps::question_key,
+        ))
+        .load(connection)
+        .map_err(|err| err.to_string())?;
+
+    let mut projects: HashMap<String, Option<Project>> = HashMap::new();
+    let updated = connection
+        .transaction::<_, diesel::result::Error, _>(|connection| {
+            let mut updated = 0;
+            for (id, antenna, project_key, theme_key, question_key) in unresolved {
+                let project = projects
+                    .entry(project_key.clone())
+                    .or_insert_with(|| get_project(connection, &project_key));
+                let option = project
+                    .as_ref()
+                    .and_then(|project| project.find_theme_by_key(&theme_key))
+                    .and_then(|theme| theme.find_question_by_key(&question_key))
+                    .zip(usize::try_from(antenna).ok())
+                    .and_then(|(question, index)| question.find_option_by_antenna_index(index));
+
+                if let Some(option) = option {
+                    diesel::update(answers::table.find(id))
+                        .set((
+                            answers::option_key.eq(option.key),
+                            answers::option_key_backfilled.eq(true),
+                        ))
+                        .execute(connection)?;
+                    updated += 1;
+                }
+            }
+            Ok(updated)
+        })
+        .map_err(|err| err.to_string())?;
+
+    save_setting(connection, OPTION_KEYS_BACKFILLED_KEY, &true)?;
+    Ok(updated)
+}
+
+/// Convert a time in microseconds since the unix epoch to a UTC timestamp
+fn micros_to_datetime(micros: u64) -> Option<time::PrimitiveDateTime> {
+    let time = time::OffsetDateTime::from_unix_timestamp_nanos(i128::from(micros) * 1000).ok()?;
+    Some(time::PrimitiveDateTime::new(time.date(), time.time()))
+}
+
+/// Convert a UTC timestamp to microseconds since the unix epoch
+pub fn datetime_to_micros(datetime: time::PrimitiveDateTime) -> u64 {
+    u64::try_from(datetime.assume_utc().unix_timestamp_nanos() / 1000).unwrap_or_default()
+}
+
+/// Add an event of a reader to our log
+///
+/// Events that happen outside of a session (e.g. while connecting before the first theme is chosen) have no session id.
+pub fn log_reader_event(
+    connection: &mut SqliteConnection,
+    session_id: Option<i32>,
+    reader_id: &str,
+    kind: &str,
+    message: &str,
+) -> Result<(), String> {
+    use crate::database::schema::reader_events;
+
+    match diesel::insert_into(reader_events::table)
+        .values((
+            reader_events::session_id.eq(session_id),
+            reader_events::reader_id.eq(reader_id),
+            reader_events::kind.eq(kind),
+            reader_events::message.eq(message),
+        ))
+        .execute(connection)
+    {
+        Ok(_) => Ok(()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// All logged reader events of a session, oldest first
+pub fn get_reader_events(
+    connection: &mut SqliteConnection,
+    session_id: &i32,
+) -> Result<Vec<ReaderLogEntry>, String> {
+    use crate::database::schema::reader_events;
+
+    reader_events::table
+        .filter(reader_events::session_id.eq(session_id))
+        .order(reader_events::id.asc())
+        .select(ReaderLogEntry::as_select())
+        .load(connection)
+        .map_err(|err| err.to_string())
+}
+
+/// Read a setting that was stored as JSON
+///
+/// If the setting does not exist (or can no longer be parsed) we return `None`, so the caller can fall back on a default.
+pub fn get_setting<T: DeserializeOwned>(connection: &mut SqliteConnection, key: &str) -> Option<T> {
+    use crate::database::schema::settings;
+
+    let value: String = settings::table
+        .filter(settings::key.eq(key))
+        .select(settings::value)
+        .first(connection)
+        .optional()
+        .ok()??;
+
+    serde_json::from_str(&value).ok()
+}
+
+pub fn save_setting<T: Serialize>(
+    connection: &mut SqliteConnection,
+    key: &str,
+    value: &T,
+) -> Result<(), String> {
+    use crate::database::schema::settings;
+
+    let value = serde_json::to_string(value).map_err(|err| err.to_string())?;
+    match diesel::replace_into(settings::table)
+        .values((settings::key.eq(key), settings::value.eq(value)))
         .execute(connection)
     {
-        Ok(answers) => Ok((step, answers)),
+        Ok(_) => Ok(()),
         Err(err) => Err(err.to_string()),
     }
 }
@@ -124,12 +966,29 @@ mod tests {
     use super::*;
     use diesel::result::Error;
 
+    /// Create a session without metadata, which most tests don't care about
+    fn create_session(
+        connection: &mut SqliteConnection,
+        project_key: &str,
+        theme_key: &str,
+        project_hash: Option<&str>,
+    ) -> Session {
+        super::create_session(
+            connection,
+            project_key,
+            theme_key,
+            project_hash,
+            &SessionMetadata::default(),
+        )
+    }
+
     fn test_db() -> SqliteConnection {
         let mut conn = SqliteConnection::establish(":memory:")
             .unwrap_or_else(|_| panic!("Could not create in memory DB"));
 
         run_migrations(&mut conn)
             .unwrap_or_else(|err| panic!("Could not run migrations, due to {}", err));
+        seed_projects(&mut conn, &Project::build_all()).unwrap();
         conn
     }
 
@@ -138,7 +997,7 @@ mod tests {
         let mut connection = test_db();
 
         connection.test_transaction::<_, Error, _>(|conn| {
-            let session = create_session(conn, "testProject", "eco");
+            let session = create_session(conn, "testProject", "eco", None);
 
             // Every test should create a new in-memory DB, so this id is always 1
             assert_eq!(session.id, 1);
@@ -147,14 +1006,245 @@ mod tests {
         })
     }
 
+    #[test]
+    fn can_save_and_edit_session_metadata() {
+        let mut connection = test_db();
+
+        connection.test_transaction::<_, Error, _>(|conn| {
+            let metadata = SessionMetadata {
+                location: Some(String::from("Ljubljana")),
+                facilitator: Some(String::from("Ana")),
+                group_name: Some(String::from("Class 3B")),
+                age_range: Some(String::from("12-14")),
+                expected_participants: Some(24),
+                notes: None,
+            };
+            let session = super::create_session(conn, "test", "theme-one", None, &metadata);
+            assert_eq!(session.metadata, metadata);
+
+            let edited = SessionMetadata {
+                facilitator: None,
+                notes: Some(String::from("Two pupils left early")),
+                ..metadata.clone()
+            };
+            let session = update_session_metadata(conn, &session.id, &edited).unwrap();
+            assert_eq!(session.metadata, edited);
+            assert_eq!(get_session_metadata(conn, &session.id), Ok(edited.clone()));
+            assert!(update_session_metadata(conn, &(session.id + 1), &edited).is_err());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn can_end_session_once() {
+        let mut connection = test_db();
+
+        connection.test_transaction::<_, Error, _>(|conn| {
+            let session = create_session(conn, "test", "theme-one", None);
+            assert_eq!(session.status, SessionStatus::InProgress);
+            assert_eq!(session.duration(), None);
+
+            let ended = end_session(conn, &session.id, SessionStatus::Completed).unwrap();
+            assert_eq!(ended.status, SessionStatus::Completed);
+            assert!(ended
+                .duration()
+                .is_some_and(|duration| !duration.is_negative()));
+
+            assert!(end_session(conn, &session.id, SessionStatus::Abandoned).is_err());
+            // Ended sessions are never abandoned afterwards
+            create_session(conn, "test", "theme-one", None);
+            assert_eq!(abandon_sessions(conn), Ok(1));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn should_never_end_or_resume_sessions_with_unknown_status() {
+        use crate::database::schema::sessions;
+        let mut connection = test_db();
+
+        connection.test_transaction::<_, Error, _>(|conn| {
+            let session = create_session(conn, "test", "theme-one", None);
+            // Sessions from before we tracked their status are migrated like this
+            diesel::update(sessions::table.find(session.id))
+                .set(sessions::status.eq(SessionStatus::Unknown))
+                .execute(conn)?;
+
+            assert_eq!(abandon_sessions(conn), Ok(0));
+            assert_eq!(get_unfinished_session(conn).map(|s| s.is_none()), Ok(true));
+            assert!(end_session(conn, &session.id, SessionStatus::Completed).is_err());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn can_find_unfinished_session_and_its_last_step() {
+        let mut connection = test_db();
+
+        connection.test_transaction::<_, Error, _>(|conn| {
+            assert_eq!(get_unfinished_session(conn).map(|s| s.is_none()), Ok(true));
+
+            let session = create_session(conn, "test", "theme-one", None);
+            assert_eq!(get_last_step(conn, &session.id), Ok(None));
+            create_step(conn, &session.id, "question-one").unwrap();
+            create_step(conn, &session.id, "question-two").unwrap();
+            let ended = create_session(conn, "test", "theme-one", None);
+            end_session(conn, &ended.id, SessionStatus::Completed).unwrap();
+
+            let unfinished = get_unfinished_session(conn).unwrap().unwrap();
+            assert_eq!(unfinished.id, session.id);
+            assert_eq!(
+                get_last_step(conn, &session.id),
+                Ok(Some(String::from("question-two")))
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn can_keep_project_snapshot_per_version() {
+        let mut connection = test_db();
+
+        connection.test_transaction::<_, Error, _>(|conn| {
+            let mut project = get_project(conn, "test").unwrap();
+            let hash = save_project_snapshot(conn, &project).unwrap();
+            // Saving the same version again reuses the snapshot
+            assert_eq!(save_project_snapshot(conn, &project), Ok(hash.clone()));
+
+            project.source = project
+                .source
+                .map(|source| source.replace("My first theme", "Renamed theme"));
+            let new_hash = save_project_snapshot(conn, &project).unwrap();
+            assert_ne!(new_hash, hash);
+
+            let session = create_session(conn, "test", "theme-one", Some(&hash));
+            assert_eq!(session.project_hash, Some(hash.clone()));
+            let snapshot = get_project_snapshot(conn, &hash).unwrap();
+            assert_eq!(
+                snapshot.themes[0].name.en,
+                Some(String::from("My first theme"))
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn can_seed_projects_again_without_changing_ids() {
+        use crate::database::schema::{questions, themes};
+
+        let mut connection = test_db();
+
+        connection.test_transaction::<_, Error, _>(|conn| {
+            let mut project = get_project(conn, "test").unwrap();
+            let theme_id: i32 = themes::table.select(themes::id).first(conn)?;
+            let question_id: i32 = questions::table.select(questions::id).first(conn)?;
+
+            project.themes[0].name.en = Some(String::from("Renamed theme"));
+            project.themes[0].questions.clear();
+            seed_projects(conn, &[project]).unwrap();
+
+            let seeded = get_project(conn, "test").unwrap();
+            assert_eq!(
+                seeded.themes[0].name.en,
+                Some(String::from("Renamed theme"))
+            );
+            assert!(seeded.themes[0].questions.is_empty());
+            assert_eq!(themes::table.select(themes::id).first(conn), Ok(theme_id));
+            // Removed questions are archived, since answers may still refer to them
+            assert_eq!(
+                questions::table
+                    .select(questions::archived)
+                    .find(question_id)
+                    .first(conn),
+                Ok(true)
+            );
+
+            // Restoring the question makes it available again
+            seed_projects(conn, &Project::build_all()).unwrap();
+            assert_eq!(
+                get_project(conn, "test").unwrap().themes[0].questions.len(),
+                1
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn should_archive_projects_that_were_removed() {
+        let mut connection = test_db();
+
+        connection.test_transaction::<_, Error, _>(|conn| {
+            seed_projects(conn, &[]).unwrap();
+
+            assert_eq!(get_projects(conn).map(|projects| projects.len()), Ok(0));
+            // Sessions of the project can still refer to it
+            assert!(get_project(conn, "test").is_some());
+
+            // Adding the file again makes the project available again
+            seed_projects(conn, &Project::build_all()).unwrap();
+            assert!(get_projects(conn)
+                .unwrap()
+                .iter()
+                .any(|project| project.key == "test"));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn can_link_sessions_steps_and_answers_to_projects() {
+        use crate::{database::schema::answers, tags::Tag};
+
+        let mut connection = test_db();
+
+        connection.test_transaction::<_, Error, _>(|conn| {
+            let question = get_project(conn, "test").unwrap().themes[0].questions[0].clone();
+            let session = create_session(conn, "test", "theme-one", None);
+            let mut map = TagsMap::new();
+            map.add_tag(Tag::new(String::from("abc123"), 1, -41));
+            let (step, _) =
+                save_step_results(conn, &session.id, &question.key, Some(&question), None, map)
+                    .unwrap();
+
+            assert!(session.theme_id.is_some());
+            assert!(step.question_id.is_some());
+            let option_id: Option<i32> = answers::table.select(answers::option_id).first(conn)?;
+            assert!(option_id.is_some());
+
+            // Rows saved before the projects were stored are linked afterwards
+            diesel::update(answers::table)
+                .set(answers::option_id.eq(None::<i32>))
+                .execute(conn)?;
+            link_to_projects(conn).unwrap();
+            assert_eq!(
+                answers::table.select(answers::option_id).first(conn),
+                Ok(option_id)
+            );
+
+            // Sessions of unknown projects are never linked
+            assert_eq!(
+                create_session(conn, "testProject", "eco", None).theme_id,
+                None
+            );
+
+            Ok(())
+        })
+    }
+
     #[test]
     fn can_create_step() {
         let mut connection = test_db();
 
         connection.test_transaction::<_, Error, _>(|conn| {
-            let session = create_session(conn, "testProject", "eco");
+            let session = create_session(conn, "testProject", "eco", None);
 
-            let step = create_step(conn, &session.id, "my-question");
+            let step = create_step(conn, &session.id, "my-question").unwrap();
 
             // Every test should create a new in-memory DB, so this id is always 1
             assert_eq!(step.id, 1);
@@ -168,8 +1258,8 @@ mod tests {
         let mut connection = test_db();
 
         connection.test_transaction::<_, Error, _>(|conn| {
-            let session = create_session(conn, "testProject", "eco");
-            let step = create_step(conn, &session.id, "my-question");
+            let session = create_session(conn, "testProject", "eco", None);
+            let step = create_step(conn, &session.id, "my-question").unwrap();
             let answer = create_answer(conn, &step.id, "option-1", "abc123");
 
             // Every test should create a new in-memory DB, so this id is always 1
@@ -184,11 +1274,11 @@ mod tests {
         let mut connection = test_db();
 
         connection.test_transaction::<_, Error, _>(|conn| {
-            let session = create_session(conn, "testProject", "eco");
+            let session = create_session(conn, "testProject", "eco", None);
 
             let map = TagsMap::random(10);
             let expected_len = &map.values().len();
-            let result = save_step_results(conn, &session.id, "my-question", map);
+            let result = save_step_results(conn, &session.id, "my-question", None, None, map);
 
             assert!(result.is_ok());
 
@@ -201,4 +1291,342 @@ mod tests {
             Ok(())
         })
     }
+
+    #[test]
+    fn can_save_read_details_with_answers() {
+        use crate::{database::schema::answers, tags::Tag};
+
+        let mut connection = test_db();
+
+        connection.test_transaction::<_, Error, _>(|conn| {
+            let session = create_session(conn, "testProject", "eco", None);
+            let mut map = TagsMap::new();
+            map.add_tag(Tag {
+                first_seen: 1_700_000_000_000_000,
+                last_seen: 1_700_000_001_500_000,
+                read_count: 7,
+                ..Tag::new(String::from("abc123"), 2, -41)
+            });
+
+            save_step_results(conn, &session.id, "my-question", None, None, map).unwrap();
+
+            let answer: Answer = answers::table
+                .select(Answer::as_select())
+                .first(conn)
+                .unwrap();
+            assert_eq!(answer.antenna, Some(2));
+            assert_eq!(answer.peak_rssi, Some(-41));
+            assert_eq!(answer.read_count, Some(7));
+            let first_seen = answer.first_seen_at.unwrap();
+            let last_seen = answer.last_seen_at.unwrap();
+            assert_eq!((last_seen - first_seen).whole_milliseconds(), 1_500);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn can_save_step_results_again() {
+        use crate::tags::Tag;
+
+        let mut connection = test_db();
+        // A map with a read of every token, so each of them results in an answer
+        let tags = |ids: &[&str]| {
+            let mut map = TagsMap::new();
+            for id in ids {
+                map.add_tag(Tag::new(id.to_string(), 1, -40));
+            }
+            map
+        };
+
+        connection.test_transaction::<_, Error, _>(|conn| {
+            let session = create_session(conn, "testProject", "eco", None);
+
+            let first = tags(&["E2001", "E2002", "E2003"]);
+            let expected = first.values().len();
+            let (step, answers) =
+                save_step_results(conn, &session.id, "my-question", None, None, first).unwrap();
+            assert_eq!(answers, expected);
+            let second = tags(&["E2001", "E2004", "E2005", "E2006", "E2007"]);
+            let expected = second.values().len();
+            let (again, answers) =
+                save_step_results(conn, &session.id, "my-question", None, None, second).unwrap();
+            assert_eq!((again.id, answers), (step.id, expected));
+            assert_eq!(
+                create_step(conn, &session.id, "my-question").unwrap().id,
+                step.id
+            );
+            // The first answers are superseded, not lost
+            assert_eq!(
+                get_step_revisions(conn, &step.id).map(|revisions| revisions.len()),
+                Ok(1)
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn can_redo_step_and_keep_superseded_answers() {
+        use crate::{database::schema::answers, tags::Tag};
+
+        let mut connection = test_db();
+
+        connection.test_transaction::<_, Error, _>(|conn| {
+            let question = get_project(conn, "test").unwrap().themes[0].questions[0].clone();
+            let session = create_session(conn, "test", "theme-one", None);
+            let mut map = TagsMap::new();
+            map.add_tag(Tag::new(String::from("abc123"), 1, -41));
+            let (step, _) =
+                save_step_results(conn, &session.id, &question.key, Some(&question), None, map)
+                    .unwrap();
+
+            let mut map = TagsMap::new();
+            map.add_tag(Tag::new(String::from("abc123"), 2, -41));
+            map.add_tag(Tag::new(String::from("def456"), 2, -41));
+            let (redone, answers) =
+                redo_step(conn, &session.id, &question.key, Some(&question), None, map).unwrap();
+
+            assert_eq!((redone.id, answers), (step.id, 2));
+            let option_keys: Vec<Option<String>> =
+                answers::table.select(answers::option_key).load(conn)?;
+            assert_eq!(
+                option_keys,
+                vec![Some(String::from("question-one-option-two")); 2]
+            );
+
+            let revisions = get_step_revisions(conn, &step.id).unwrap();
+            assert_eq!(revisions.len(), 1);
+            let superseded: Vec<serde_json::Value> =
+                serde_json::from_str(&revisions[0].answers).unwrap();
+            assert_eq!(superseded.len(), 1);
+            assert_eq!(superseded[0]["option_key"], "question-one-option-one");
+            // Only steps that were saved before can be redone
+            assert!(redo_step(conn, &session.id, "unknown", None, None, TagsMap::new()).is_err());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn can_undo_last_step_and_restore_its_tags() {
+        use crate::{database::schema::answers, tags::Tag};
+
+        let mut connection = test_db();
+
+        connection.test_transaction::<_, Error, _>(|conn| {
+            let session = create_session(conn, "testProject", "eco", None);
+            let mut map = TagsMap::new();
+            map.add_tag(Tag::new(String::from("def456"), 1, -41));
+            save_step_results(conn, &session.id, "first", None, None, map).unwrap();
+            let mut map = TagsMap::new();
+            map.add_tag(Tag {
+                first_seen: 1_700_000_000_000_000,
+                last_seen: 1_700_000_001_500_000,
+                read_count: 7,
+                ..Tag::new(String::from("abc123"), 2, -41)
+            });
+            save_step_results(conn, &session.id, "second", None, None, map).unwrap();
+
+            let (step, tags_map) = undo_last_step(conn, &session.id).unwrap().unwrap();
+
+            assert_eq!(step.question_key, "second");
+            let tag = tags_map.get("abc123").unwrap();
+            assert_eq!((tag.antenna, tag.peak_strength), (2, -41));
+            assert_eq!(
+                (tag.first_seen, tag.last_seen, tag.read_count),
+                (1_700_000_000_000_000, 1_700_000_001_500_000, 7)
+            );
+            assert_eq!(answers::table.count().get_result(conn), Ok(1));
+            // The step and what was saved for it are kept
+            assert!(find_step(conn, &session.id, "second")?.is_some_and(|s| s.voided_at.is_some()));
+            assert_eq!(get_step_revisions(conn, &step.id).map(|r| r.len()), Ok(1));
+            assert_eq!(
+                get_answered_questions(conn, &session.id),
+                Ok(HashSet::from([String::from("first")]))
+            );
+            assert_eq!(
+                get_last_step(conn, &session.id),
+                Ok(Some(String::from("first")))
+            );
+
+            // The step can be saved again afterwards
+            let (saved, answers) =
+                save_step_results(conn, &session.id, "second", None, None, tags_map).unwrap();
+            assert_eq!((saved.id, saved.voided_at, answers), (step.id, None, 1));
+            assert_eq!(get_step_revisions(conn, &step.id).map(|r| r.len()), Ok(1));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn can_save_voting_window_with_step() {
+        use crate::{database::schema::answers, tags::Tag};
+
+        let mut connection = test_db();
+
+        connection.test_transaction::<_, Error, _>(|conn| {
+            let session = create_session(conn, "testProject", "eco", None);
+            let window = StepWindow {
+                opened_at: 1_700_000_000_000_000,
+                closed_at: 1_700_000_010_000_000,
+            };
+            let mut map = TagsMap::new();
+            map.add_tag(Tag {
+                received_at: 1_700_000_002_250_000,
+                // The clock of the reader is ahead, which should not change the time to vote
+                first_seen: 1_700_000_062_250_000,
+                ..Tag::new(String::from("abc123"), 2, -41)
+            });
+            map.add_tag(Tag {
+                received_at: 1_699_999_999_000_000,
+                ..Tag::new(String::from("def456"), 1, -41)
+            });
+
+            let (step, _) =
+                save_step_results(conn, &session.id, "my-question", None, Some(window), map)
+                    .unwrap();
+
+            let duration = step.closed_at.unwrap() - step.opened_at.unwrap();
+            assert_eq!(duration.whole_seconds(), 10);
+            let mut times: Vec<(String, Option<i32>)> = answers::table
+                .select((answers::token_key, answers::time_to_vote_ms))
+                .load(conn)?;
+            times.sort();
+            assert_eq!(
+                times,
+                vec![
+                    (String::from("abc123"), Some(2_250)),
+                    // Seen before the vote opened
+                    (String::from("def456"), Some(0)),
+                ]
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn can_save_option_key_of_answers() {
+        use crate::{database::schema::answers, tags::Tag};
+
+        let mut connection = test_db();
+
+        connection.test_transaction::<_, Error, _>(|conn| {
+            let question = get_project(conn, "test").unwrap().themes[0].questions[0].clone();
+            let session = create_session(conn, "test", "theme-one", None);
+            let mut map = TagsMap::new();
+            map.add_tag(Tag::new(String::from("abc123"), 2, -41));
+            map.add_tag(Tag::new(String::from("def456"), 3, -41));
+
+            save_step_results(conn, &session.id, &question.key, Some(&question), None, map)
+                .unwrap();
+
+            let mut saved: Vec<(String, Option<String>, Option<i32>)> = answers::table
+                .select((answers::token_key, answers::option_key, answers::antenna))
+                .load(conn)
+                .unwrap();
+            saved.sort();
+            assert_eq!(
+                saved,
+                vec![
+                    (
+                        String::from("abc123"),
+                        Some(String::from("question-one-option-two")),
+                        Some(2)
+                    ),
+                    // The test question only has two options
+                    (String::from("def456"), None, Some(3)),
+                ]
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn can_backfill_option_keys_once() {
+        use crate::database::schema::answers;
+
+        let mut connection = test_db();
+
+        connection.test_transaction::<_, Error, _>(|conn| {
+            let session = create_session(conn, "test", "theme-one", None);
+            let step = create_step(conn, &session.id, "question-one").unwrap();
+            // An answer as it was saved before we stored option keys
+            diesel::insert_into(answers::table)
+                .values((
+                    answers::step_id.eq(step.id),
+                    answers::token_key.eq("abc123"),
+                    answers::antenna.eq(1),
+                ))
+                .execute(conn)?;
+
+            assert_eq!(backfill_option_keys(conn), Ok(1));
+            let (option_key, backfilled): (Option<String>, bool) = answers::table
+                .select((answers::option_key, answers::option_key_backfilled))
+                .first(conn)?;
+            assert_eq!(option_key, Some(String::from("question-one-option-one")));
+            assert!(backfilled);
+
+            // Once done, we never resolve answers again
+            diesel::update(answers::table)
+                .set(answers::option_key.eq(None::<String>))
+                .execute(conn)?;
+            assert_eq!(backfill_option_keys(conn), Ok(0));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn can_log_reader_events_per_session() {
+        let mut connection = test_db();
+
+        connection.test_transaction::<_, Error, _>(|conn| {
+            let session = create_session(conn, "testProject", "eco", None);
+            let other_session = create_session(conn, "testProject", "eco", None);
+
+            log_reader_event(conn, None, "fx9600749620", "State", "Connecting").unwrap();
+            log_reader_event(conn, Some(session.id), "fx9600749620", "State", "Reading").unwrap();
+            log_reader_event(
+                conn,
+                Some(session.id),
+                "fx9600749620",
+                "Error",
+                "LostConnection",
+            )
+            .unwrap();
+            log_reader_event(conn, Some(other_session.id), "fx9600749620", "Stopped", "").unwrap();
+
+            let events = get_reader_events(conn, &session.id).unwrap();
+            let kinds: Vec<&str> = events.iter().map(|event| event.kind.as_str()).collect();
+            assert_eq!(kinds, vec!["State", "Error"]);
+            assert_eq!(events[0].message, "Reading");
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn can_save_and_read_settings() {
+        let mut connection = test_db();
+
+        connection.test_transaction::<_, Error, _>(|conn| {
+            assert_eq!(get_setting::<Vec<u16>>(conn, "my-setting"), None);
+
+            save_setting(conn, "my-setting", &vec![1, 2]).unwrap();
+            assert_eq!(
+                get_setting::<Vec<u16>>(conn, "my-setting"),
+                Some(vec![1, 2])
+            );
+
+            // Saving again should overwrite the previous value
+            save_setting(conn, "my-setting", &vec![3]).unwrap();
+            assert_eq!(get_setting::<Vec<u16>>(conn, "my-setting"), Some(vec![3]));
+
+            Ok(())
+        })
+    }
 }
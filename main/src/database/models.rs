@@ -1,4 +1,12 @@
-use diesel::prelude::*;
+use diesel::{
+    deserialize::{self, FromSql, FromSqlRow},
+    expression::AsExpression,
+    prelude::*,
+    serialize::{self, IsNull, Output, ToSql},
+    sql_types::Text,
+    sqlite::{Sqlite, SqliteValue},
+};
+use std::fmt;
 use time;
 
 #[derive(Queryable, Selectable, Debug)]
@@ -9,6 +17,91 @@ pub struct Session {
     pub created_at: time::PrimitiveDateTime,
     pub project_key: String,
     pub theme_key: String,
+    /// The hash of the snapshot of the project at the start of this session
+    pub project_hash: Option<String>,
+    pub theme_id: Option<i32>,
+    pub ended_at: Option<time::PrimitiveDateTime>,
+    pub status: SessionStatus,
+    #[diesel(embed)]
+    pub metadata: SessionMetadata,
+}
+
+/// The context of a session, which the facilitator can fill in when starting it or afterwards
+#[derive(
+    Queryable,
+    Selectable,
+    Insertable,
+    AsChangeset,
+    Debug,
+    Clone,
+    Default,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[diesel(table_name = crate::database::schema::sessions)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+// Clearing a field while editing should also clear it in the database
+#[diesel(treat_none_as_null = true)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SessionMetadata {
+    pub location: Option<String>,
+    pub facilitator: Option<String>,
+    pub group_name: Option<String>,
+    /// The age range of the group, e.g. `12-14`
+    pub age_range: Option<String>,
+    pub expected_participants: Option<i32>,
+    pub notes: Option<String>,
+}
+
+impl Session {
+    /// How long the session took, or `None` while it is in progress (or when we don't know when it ended)
+    pub fn duration(&self) -> Option<time::Duration> {
+        self.ended_at.map(|ended_at| ended_at - self.created_at)
+    }
+}
+
+/// How a session ended, which we store by the name of its variant
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Text)]
+pub enum SessionStatus {
+    InProgress,
+    /// Every question of the theme was answered
+    Completed,
+    /// The session ended before every question was answered, e.g. because another session started
+    Abandoned,
+    /// The session was recorded before we kept track of how sessions end
+    Unknown,
+}
+
+impl fmt::Display for SessionStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionStatus::InProgress => write!(f, "InProgress"),
+            SessionStatus::Completed => write!(f, "Completed"),
+            SessionStatus::Abandoned => write!(f, "Abandoned"),
+            SessionStatus::Unknown => write!(f, "Unknown"),
+        }
+    }
+}
+
+impl FromSql<Text, Sqlite> for SessionStatus {
+    fn from_sql(value: SqliteValue<'_, '_, '_>) -> deserialize::Result<Self> {
+        match <String as FromSql<Text, Sqlite>>::from_sql(value)?.as_str() {
+            "InProgress" => Ok(SessionStatus::InProgress),
+            "Completed" => Ok(SessionStatus::Completed),
+            "Abandoned" => Ok(SessionStatus::Abandoned),
+            "Unknown" => Ok(SessionStatus::Unknown),
+            status => Err(format!("Unrecognized session status: {}", status).into()),
+        }
+    }
+}
+
+impl ToSql<Text, Sqlite> for SessionStatus {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Sqlite>) -> serialize::Result {
+        out.set_value(self.to_string());
+        Ok(IsNull::No)
+    }
 }
 
 #[derive(Queryable, Selectable, Debug)]
@@ -19,14 +112,103 @@ pub struct Step {
     pub created_at: time::PrimitiveDateTime,
     pub session_id: i32,
     pub question_key: String,
+    pub question_id: Option<i32>,
+    /// When voting on this step opened and closed, if the step was opened explicitly
+    pub opened_at: Option<time::PrimitiveDateTime>,
+    pub closed_at: Option<time::PrimitiveDateTime>,
+    /// When this step was undone, until it is saved again
+    pub voided_at: Option<time::PrimitiveDateTime>,
 }
 
-#[derive(Queryable, Selectable, Debug)]
+#[derive(Queryable, Selectable, Debug, serde::Serialize)]
 #[diesel(table_name = crate::database::schema::answers)]
 #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
 pub struct Answer {
     pub id: i32,
     pub step_id: i32,
-    pub option_key: String,
+    /// The key of the chosen option, or `None` if the antenna did not match an option of the question
+    pub option_key: Option<String>,
     pub token_key: String,
+    /// The details of the reads of the token. These are `None` for answers that were saved before we kept them.
+    pub antenna: Option<i32>,
+    pub peak_rssi: Option<i32>,
+    pub first_seen_at: Option<time::PrimitiveDateTime>,
+    pub last_seen_at: Option<time::PrimitiveDateTime>,
+    pub read_count: Option<i32>,
+    pub option_id: Option<i32>,
+    /// How long it took before the token was first seen after voting opened
+    pub time_to_vote_ms: Option<i32>,
+    /// Whether the option key was resolved from the antenna afterwards, using the project as it was at that time
+    pub option_key_backfilled: bool,
+}
+
+/// A version of a step that was replaced by recording the step again
+#[derive(Queryable, Selectable, Debug, serde::Serialize)]
+#[diesel(table_name = crate::database::schema::step_revisions)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct StepRevision {
+    pub id: i32,
+    pub step_id: i32,
+    pub superseded_at: time::PrimitiveDateTime,
+    pub opened_at: Option<time::PrimitiveDateTime>,
+    pub closed_at: time::PrimitiveDateTime,
+    /// The superseded answers, as a JSON list of `Answer`s
+    pub answers: String,
+}
+
+#[derive(Queryable, Selectable, Debug, serde::Serialize)]
+#[diesel(table_name = crate::database::schema::reader_events)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ReaderLogEntry {
+    pub id: i32,
+    pub created_at: time::PrimitiveDateTime,
+    pub session_id: Option<i32>,
+    pub reader_id: String,
+    pub kind: String,
+    pub message: String,
+}
+
+/// A project as it is stored in the database. Translated properties are stored as JSON.
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = crate::database::schema::projects)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ProjectRecord {
+    pub key: String,
+    pub name: String,
+    pub available_languages: String,
+    /// The contents of the project file
+    pub source: Option<String>,
+}
+
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = crate::database::schema::themes)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ThemeRecord {
+    pub id: i32,
+    pub project_key: String,
+    pub key: String,
+    pub name: String,
+}
+
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = crate::database::schema::questions)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct QuestionRecord {
+    pub id: i32,
+    pub theme_id: i32,
+    pub key: String,
+    pub title: String,
+    pub explanation: Option<String>,
+    pub question_type: Option<String>,
+}
+
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = crate::database::schema::options)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct OptionRecord {
+    pub id: i32,
+    pub question_id: i32,
+    pub key: String,
+    pub value: String,
+    pub correct: Option<bool>,
 }
@@ -4,8 +4,71 @@ diesel::table! {
     answers (id) {
         id -> Integer,
         token_key -> Text,
-        option_key -> Text,
+        option_key -> Nullable<Text>,
         step_id -> Integer,
+        antenna -> Nullable<Integer>,
+        peak_rssi -> Nullable<Integer>,
+        first_seen_at -> Nullable<Timestamp>,
+        last_seen_at -> Nullable<Timestamp>,
+        read_count -> Nullable<Integer>,
+        option_id -> Nullable<Integer>,
+        time_to_vote_ms -> Nullable<Integer>,
+        option_key_backfilled -> Bool,
+    }
+}
+
+diesel::table! {
+    options (id) {
+        id -> Integer,
+        question_id -> Integer,
+        key -> Text,
+        value -> Text,
+        correct -> Nullable<Bool>,
+        position -> Integer,
+        archived -> Bool,
+    }
+}
+
+diesel::table! {
+    project_snapshots (hash) {
+        hash -> Text,
+        created_at -> Timestamp,
+        project_key -> Text,
+        content -> Text,
+    }
+}
+
+diesel::table! {
+    projects (key) {
+        key -> Text,
+        name -> Text,
+        available_languages -> Text,
+        source -> Nullable<Text>,
+        archived -> Bool,
+    }
+}
+
+diesel::table! {
+    questions (id) {
+        id -> Integer,
+        theme_id -> Integer,
+        key -> Text,
+        title -> Text,
+        explanation -> Nullable<Text>,
+        question_type -> Nullable<Text>,
+        position -> Integer,
+        archived -> Bool,
+    }
+}
+
+diesel::table! {
+    reader_events (id) {
+        id -> Integer,
+        created_at -> Timestamp,
+        session_id -> Nullable<Integer>,
+        reader_id -> Text,
+        kind -> Text,
+        message -> Text,
     }
 }
 
@@ -15,6 +78,34 @@ diesel::table! {
         created_at -> Timestamp,
         project_key -> Text,
         theme_key -> Text,
+        project_hash -> Nullable<Text>,
+        theme_id -> Nullable<Integer>,
+        ended_at -> Nullable<Timestamp>,
+        status -> Text,
+        location -> Nullable<Text>,
+        facilitator -> Nullable<Text>,
+        group_name -> Nullable<Text>,
+        age_range -> Nullable<Text>,
+        expected_participants -> Nullable<Integer>,
+        notes -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    settings (key) {
+        key -> Text,
+        value -> Text,
+    }
+}
+
+diesel::table! {
+    step_revisions (id) {
+        id -> Integer,
+        step_id -> Integer,
+        superseded_at -> Timestamp,
+        opened_at -> Nullable<Timestamp>,
+        closed_at -> Timestamp,
+        answers -> Text,
     }
 }
 
@@ -24,10 +115,42 @@ diesel::table! {
         created_at -> Timestamp,
         question_key -> Text,
         session_id -> Integer,
+        question_id -> Nullable<Integer>,
+        opened_at -> Nullable<Timestamp>,
+        closed_at -> Nullable<Timestamp>,
+        voided_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    themes (id) {
+        id -> Integer,
+        project_key -> Text,
+        key -> Text,
+        name -> Text,
+        position -> Integer,
+        archived -> Bool,
     }
 }
 
 diesel::joinable!(answers -> steps (step_id));
+diesel::joinable!(options -> questions (question_id));
+diesel::joinable!(questions -> themes (theme_id));
+diesel::joinable!(reader_events -> sessions (session_id));
+diesel::joinable!(step_revisions -> steps (step_id));
 diesel::joinable!(steps -> sessions (session_id));
+diesel::joinable!(themes -> projects (project_key));
 
-diesel::allow_tables_to_appear_in_same_query!(answers, sessions, steps,);
+diesel::allow_tables_to_appear_in_same_query!(
+    answers,
+    options,
+    project_snapshots,
+    projects,
+    questions,
+    reader_events,
+    sessions,
+    settings,
+    step_revisions,
+    steps,
+    themes,
+);
@@ -1,21 +1,18 @@
 use diesel::{ExpressionMethods, RunQueryDsl};
 use diesel::{QueryDsl, SelectableHelper, SqliteConnection};
 use rust_xlsxwriter::{ExcelDateTime, Format, Workbook, Worksheet, XlsxError};
+use std::collections::HashMap;
 
 use crate::database::{
+    get_project_snapshot,
     models::{Answer, Session, Step},
     schema::{answers, sessions, steps},
 };
 use crate::error::{GeneralError, GeneralErrorKind};
 use crate::projects::Project;
-use std::collections::HashMap;
+use crate::tags::tokens;
 
 const BATCH_SIZE: i64 = 10000;
-const TOKEN_LIST: &str = include_str!("../../data/tokens/list.json");
-
-fn tokens() -> HashMap<String, String> {
-    serde_json::from_str(TOKEN_LIST).unwrap()
-}
 
 pub fn export_project_data(
     connection: &mut SqliteConnection,
@@ -23,7 +20,7 @@ pub fn export_project_data(
     project_key: String,
 ) -> Result<(), GeneralError> {
     let mut workbook = Workbook::new();
-    let Some(project) = Project::find_by_key(&project_key) else {
+    let Some(project) = Project::find_by_key(connection, &project_key) else {
         return Err(GeneralError {
             kind: GeneralErrorKind::IncorrectProject(project_key),
             message: String::new(),
@@ -48,9 +45,16 @@ pub fn export_project_data(
         .get_result(connection)
         .unwrap();
     let mut page = 0;
+    let mut snapshots = HashMap::new();
 
     while page * BATCH_SIZE < count {
-        match fetch_batch_and_write(connection, worksheet, &project, page * BATCH_SIZE) {
+        match fetch_batch_and_write(
+            connection,
+            worksheet,
+            &project,
+            &mut snapshots,
+            page * BATCH_SIZE,
+        ) {
             Ok(()) => page += 1,
             Err(err) => {
                 return Err(GeneralError {
@@ -74,10 +78,30 @@ fn get_token_type_from_key(input: &str) -> String {
     }
 }
 
+/// The project as it was during the session
+///
+/// Sessions from before we kept snapshots (or with a snapshot we can no longer read) use the current project.
+fn project_of_session<'a>(
+    connection: &mut SqliteConnection,
+    snapshots: &'a mut HashMap<String, Option<Project>>,
+    current: &'a Project,
+    session: &Session,
+) -> &'a Project {
+    let Some(hash) = &session.project_hash else {
+        return current;
+    };
+    snapshots
+        .entry(hash.clone())
+        .or_insert_with(|| get_project_snapshot(connection, hash))
+        .as_ref()
+        .unwrap_or(current)
+}
+
 fn fetch_batch_and_write(
     connection: &mut SqliteConnection,
     worksheet: &mut Worksheet,
     project: &Project,
+    snapshots: &mut HashMap<String, Option<Project>>,
     offset: i64,
 ) -> Result<(), XlsxError> {
     let results: Vec<(Answer, Step, Session)> = answers::table
@@ -91,6 +115,8 @@ fn fetch_batch_and_write(
         .unwrap();
 
     let format = Format::new().set_num_format("yyyy-mm-dd hh:mm:ss");
+    // Reads are only milliseconds apart, so we keep the fractional seconds of their timestamps
+    let precise_format = Format::new().set_num_format("yyyy-mm-dd hh:mm:ss.000");
     let language = project.available_languages.first().unwrap();
 
     for (row, (answer, step, session)) in results.iter().enumerate() {
@@ -99,17 +125,15 @@ fn fetch_batch_and_write(
             ExcelDateTime::from_timestamp(step.created_at.assume_utc().unix_timestamp()).unwrap();
 
         // If somewhere in the chain of theme, question, ... when can't find an object, we simply ignore this and print nothing
-        let theme = project.find_theme_by_key(&session.theme_key);
+        let theme = project_of_session(connection, snapshots, project, session)
+            .find_theme_by_key(&session.theme_key);
         let question = theme
             .as_ref()
             .and_then(|t| t.find_question_by_key(&step.question_key));
-        let option = match answer.option_key.parse::<usize>() {
-            Ok(index) => question
-                .as_ref()
-                .and_then(|q| q.find_option_by_antenna_index(index)),
-            // If we can't parse the value, we simply ignore this
-            Err(_) => None,
-        };
+        let option = question
+            .as_ref()
+            .zip(answer.option_key.as_ref())
+            .and_then(|(q, key)| q.find_option_by_key(key));
 
         worksheet.write(row, 0, &session.project_key)?;
         worksheet.write(row, 1, session.id)?;
@@ -120,13 +144,56 @@ fn fetch_batch_and_write(
         worksheet.write_with_format(row, 6, created_at, &format)?;
         worksheet.write(row, 7, &answer.token_key)?;
         worksheet.write(row, 8, get_token_type_from_key(&answer.token_key))?;
-        worksheet.write(row, 9, &answer.option_key)?;
+        worksheet.write(row, 9, answer.option_key.as_deref())?;
         worksheet.write(row, 10, option.and_then(|o| o.value.get(&language)))?;
+        worksheet.write(row, 11, answer.antenna)?;
+        worksheet.write(row, 12, answer.peak_rssi)?;
+        worksheet.write_with_format(
+            row,
+            13,
+            answer.first_seen_at.and_then(to_excel),
+            &precise_format,
+        )?;
+        worksheet.write_with_format(
+            row,
+            14,
+            answer.last_seen_at.and_then(to_excel),
+            &precise_format,
+        )?;
+        worksheet.write(row, 15, answer.read_count)?;
+        worksheet.write(row, 16, session.status.to_string())?;
+        worksheet.write(
+            row,
+            17,
+            session.duration().map(|duration| duration.as_seconds_f64()),
+        )?;
+        worksheet.write_with_format(row, 18, step.opened_at.and_then(to_excel), &precise_format)?;
+        worksheet.write_with_format(row, 19, step.closed_at.and_then(to_excel), &precise_format)?;
+        worksheet.write(row, 20, answer.time_to_vote_ms)?;
+        worksheet.write(row, 21, session.metadata.location.as_deref())?;
+        worksheet.write(row, 22, session.metadata.facilitator.as_deref())?;
+        worksheet.write(row, 23, session.metadata.group_name.as_deref())?;
+        worksheet.write(row, 24, session.metadata.age_range.as_deref())?;
+        worksheet.write(row, 25, session.metadata.expected_participants)?;
+        worksheet.write(row, 26, session.metadata.notes.as_deref())?;
+        worksheet.write(row, 27, answer.option_key_backfilled)?;
     }
 
     Ok(())
 }
 
+/// Convert a timestamp to Excel, including its fractional seconds
+fn to_excel(datetime: time::PrimitiveDateTime) -> Option<ExcelDateTime> {
+    let seconds = datetime.second() as f64 + datetime.nanosecond() as f64 / 1_000_000_000.0;
+    ExcelDateTime::from_ymd(
+        datetime.year().try_into().ok()?,
+        datetime.month().into(),
+        datetime.day(),
+    )
+    .and_then(|date| date.and_hms(datetime.hour().into(), datetime.minute(), seconds))
+    .ok()
+}
+
 fn write_headers(worksheet: &mut Worksheet) -> Result<(), XlsxError> {
     worksheet.write(0, 0, "Project key")?;
     worksheet.write(0, 1, "Session ID")?;
@@ -139,6 +206,23 @@ fn write_headers(worksheet: &mut Worksheet) -> Result<(), XlsxError> {
     worksheet.write(0, 8, "Token Identifier")?;
     worksheet.write(0, 9, "Option key")?;
     worksheet.write(0, 10, "Option value")?;
+    worksheet.write(0, 11, "Antenna")?;
+    worksheet.write(0, 12, "Peak RSSI")?;
+    worksheet.write(0, 13, "First seen (UTC)")?;
+    worksheet.write(0, 14, "Last seen (UTC)")?;
+    worksheet.write(0, 15, "Read count")?;
+    worksheet.write(0, 16, "Session status")?;
+    worksheet.write(0, 17, "Session duration (seconds)")?;
+    worksheet.write(0, 18, "Voting opened (UTC)")?;
+    worksheet.write(0, 19, "Voting closed (UTC)")?;
+    worksheet.write(0, 20, "Time to vote (ms)")?;
+    worksheet.write(0, 21, "Location")?;
+    worksheet.write(0, 22, "Facilitator")?;
+    worksheet.write(0, 23, "Group")?;
+    worksheet.write(0, 24, "Age range")?;
+    worksheet.write(0, 25, "Expected participants")?;
+    worksheet.write(0, 26, "Notes")?;
+    worksheet.write(0, 27, "Option key backfilled")?;
     Ok(())
 }
 
@@ -158,4 +242,31 @@ mod tests {
 
         assert_eq!(value, "bl_bl");
     }
+
+    #[test]
+    fn should_keep_milliseconds_when_exporting_timestamps() {
+        let datetime = time::PrimitiveDateTime::new(
+            time::Date::from_calendar_date(2024, time::Month::May, 1).unwrap(),
+            time::Time::from_hms_milli(12, 30, 15, 250).unwrap(),
+        );
+
+        let excel = to_excel(datetime).unwrap();
+
+        assert_eq!(
+            excel.to_excel(),
+            ExcelDateTime::from_ymd(2024, 5, 1)
+                .unwrap()
+                .and_hms(12, 30, 15.25)
+                .unwrap()
+                .to_excel()
+        );
+        assert_ne!(
+            excel.to_excel(),
+            ExcelDateTime::from_ymd(2024, 5, 1)
+                .unwrap()
+                .and_hms(12, 30, 15)
+                .unwrap()
+                .to_excel()
+        );
+    }
 }
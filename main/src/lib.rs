@@ -5,14 +5,36 @@ pub mod projects;
 pub mod reader;
 pub mod tags;
 
-use database::{create_session, save_step_results, setup_database};
+use database::{
+    abandon_sessions, create_session, datetime_to_micros, end_session, get_answered_questions,
+    get_last_step, get_reader_events, get_session_metadata, get_setting, get_unfinished_session,
+    log_reader_event,
+    models::{ReaderLogEntry, SessionMetadata, SessionStatus},
+    redo_step, save_project_snapshot, save_setting, save_step_results, setup_database,
+    undo_last_step, update_session_metadata, StepWindow,
+};
 use diesel::prelude::*;
-use error::{GeneralError, GeneralErrorKind};
+use error::{CirclesError, GeneralError, GeneralErrorKind};
 use projects::{Project, Theme};
-use reader::{LLRPReader, MockReader, Reader, ReaderError, ReaderProtocol};
-use std::{env, path::PathBuf};
-use tags::TagsMap;
-use tauri::AppHandle;
+use reader::{
+    calibration::{
+        Calibration, CalibrationReport, CalibrationSample, CALIBRATION_DWELL,
+        DEFAULT_MAX_POWER_INDEX,
+    },
+    registry::DEFAULT_BACKEND,
+    settings::{DEFAULT_ANTENNAS, READER_BACKEND_KEY, READER_SETTINGS_KEY},
+    tauri_sink, update_state, Reader, ReaderConnection, ReaderDiagnostics, ReaderError,
+    ReaderErrorKind, ReaderInfo, ReaderLogKind, ReaderRegistry, ReaderSettings, ReaderState,
+    TagMerger,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+    sync::atomic::Ordering,
+    thread::sleep,
+};
+use tags::{now_micros, TagsMap};
+use tauri::{AppHandle, Manager};
 
 #[derive(Clone)]
 pub struct CurrentSession {
@@ -20,31 +42,80 @@ pub struct CurrentSession {
     pub theme: Theme,
 }
 
+/// A step that was undone, with the tags that were saved for it
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UndoneStep {
+    pub question_key: String,
+    pub tags_map: TagsMap,
+}
+
+/// A session that can be continued after the app was closed during it
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResumableSession {
+    pub session_id: i32,
+    pub project_key: String,
+    pub theme_key: String,
+    /// The key of the question that was answered last, if any
+    pub last_step: Option<String>,
+}
+
 pub struct GlobalState {
     pub database_connection: std::sync::Mutex<SqliteConnection>,
     pub current_project: std::sync::Mutex<Option<Project>>,
     pub current_session: std::sync::Mutex<Option<CurrentSession>>,
-    pub reader: std::sync::Mutex<Option<Reader>>,
+    /// All connected readers, by hostname
+    pub readers: std::sync::Mutex<BTreeMap<String, Reader>>,
+    pub reader_registry: ReaderRegistry,
+    pub reader_states: std::sync::Mutex<BTreeMap<String, ReaderState>>,
+    /// The backend we use when connecting to a reader by hostname
+    pub reader_backend: std::sync::Mutex<String>,
+    pub reader_settings: std::sync::Mutex<ReaderSettings>,
+    pub calibration: std::sync::Mutex<Calibration>,
+    /// Whether we are calibrating, in which case reads skip the minimum RSSI
+    pub calibrating: std::sync::atomic::AtomicBool,
+    pub diagnostics: std::sync::Mutex<ReaderDiagnostics>,
+    pub tag_merger: std::sync::Mutex<TagMerger>,
     pub tags_map: std::sync::Arc<std::sync::Mutex<TagsMap>>,
+    /// When the vote on the current step opened, in microseconds since the unix epoch
+    pub step_opened_at: std::sync::Mutex<Option<u64>>,
 }
 
 impl GlobalState {
     pub fn build(database_location: PathBuf) -> Result<GlobalState, Box<dyn std::error::Error>> {
-        let connection = setup_database(&database_location)?;
+        let mut connection = setup_database(&database_location)?;
+        let reader_settings: ReaderSettings =
+            get_setting(&mut connection, READER_SETTINGS_KEY).unwrap_or_default();
+        let reader_backend: String = get_setting(&mut connection, READER_BACKEND_KEY)
+            .unwrap_or_else(|| String::from(DEFAULT_BACKEND));
 
         let state = GlobalState {
             database_connection: std::sync::Mutex::new(connection),
             current_project: std::sync::Mutex::new(None),
             current_session: std::sync::Mutex::new(None),
-            reader: std::sync::Mutex::new(None),
+            readers: std::sync::Mutex::new(BTreeMap::new()),
+            reader_registry: ReaderRegistry::default(),
+            reader_states: std::sync::Mutex::new(BTreeMap::new()),
+            reader_backend: std::sync::Mutex::new(reader_backend),
+            reader_settings: std::sync::Mutex::new(reader_settings),
+            calibration: std::sync::Mutex::new(Calibration::default()),
+            calibrating: std::sync::atomic::AtomicBool::new(false),
+            diagnostics: std::sync::Mutex::new(ReaderDiagnostics::default()),
+            tag_merger: std::sync::Mutex::new(TagMerger::default()),
             tags_map: std::sync::Arc::new(std::sync::Mutex::new(TagsMap::new())),
+            step_opened_at: std::sync::Mutex::new(None),
         };
 
         Ok(state)
     }
 
     pub fn select_project(&self, project_key: String) -> Result<(), GeneralError> {
-        match Project::find_by_key(&project_key) {
+        let project = {
+            let mut connection = self.database_connection.lock().unwrap();
+            Project::find_by_key(&mut *connection, &project_key)
+        };
+        match project {
             Some(project) => {
                 let mut lock = self.current_project.lock().unwrap();
                 *lock = Some(project.clone());
@@ -57,7 +128,11 @@ impl GlobalState {
         }
     }
 
-    pub fn start_session(&self, theme_key: String) -> Result<i32, String> {
+    pub fn start_session(
+        &self,
+        theme_key: String,
+        metadata: SessionMetadata,
+    ) -> Result<i32, String> {
         let mut project = self.current_project.lock().unwrap();
         let project = match &mut *project {
             Some(p) => p,
@@ -72,7 +147,15 @@ impl GlobalState {
         let mut connection = self.database_connection.lock().unwrap();
         let mut current_session = self.current_session.lock().unwrap();
 
-        let session = create_session(&mut *connection, &project.key, &theme_key);
+        abandon_sessions(&mut *connection)?;
+        let project_hash = save_project_snapshot(&mut *connection, project)?;
+        let session = create_session(
+            &mut *connection,
+            &project.key,
+            &theme_key,
+            Some(&project_hash),
+            &metadata,
+        );
         *current_session = Some(CurrentSession {
             session_id: session.id,
             theme: theme,
@@ -82,39 +165,424 @@ impl GlobalState {
         Ok(session.id)
     }
 
+    /// Edit the metadata of a session, e.g. to add notes after it ended
+    pub fn update_session_metadata(
+        &self,
+        session_id: i32,
+        metadata: SessionMetadata,
+    ) -> Result<SessionMetadata, String> {
+        let mut connection = self.database_connection.lock().unwrap();
+        update_session_metadata(&mut *connection, &session_id, &metadata)
+            .map(|session| session.metadata)
+    }
+
+    pub fn session_metadata(&self, session_id: i32) -> Result<SessionMetadata, String> {
+        let mut connection = self.database_connection.lock().unwrap();
+        get_session_metadata(&mut *connection, &session_id)
+    }
+
+    /// The session that was in progress when the app was closed, if there is one
+    ///
+    /// Everything we need to resume it (its project, theme and steps) is already in the database.
+    pub fn resumable_session(&self) -> Result<Option<ResumableSession>, String> {
+        let mut connection = self.database_connection.lock().unwrap();
+        let Some(session) = get_unfinished_session(&mut *connection)? else {
+            return Ok(None);
+        };
+
+        Ok(Some(ResumableSession {
+            last_step: get_last_step(&mut *connection, &session.id)?,
+            session_id: session.id,
+            project_key: session.project_key,
+            theme_key: session.theme_key,
+        }))
+    }
+
+    /// Continue an unfinished session, so the next steps are saved in the same session
+    ///
+    /// The project of the session has to be selected first, since that also connects the readers.
+    pub fn resume_session(&self, session_id: i32) -> Result<ResumableSession, String> {
+        let resumable = match self.resumable_session()? {
+            Some(resumable) if resumable.session_id == session_id => resumable,
+            _ => return Err(format!("Session {} can not be resumed", session_id)),
+        };
+
+        let project = self.current_project.lock().unwrap();
+        let theme = project
+            .as_ref()
+            .filter(|project| project.key == resumable.project_key)
+            .and_then(|project| project.find_theme_by_key(&resumable.theme_key))
+            .ok_or_else(|| String::from("Please select the project of this session first"))?;
+
+        *self.current_session.lock().unwrap() = Some(CurrentSession { session_id, theme });
+        Ok(resumable)
+    }
+
+    /// End the current session, returning whether every question was answered
+    ///
+    /// Questions without options have nothing to answer, so they don't need a step to complete the session.
+    pub fn end_session(&self) -> Result<SessionStatus, String> {
+        let mut connection = self.database_connection.lock().unwrap();
+        let mut current_session = self.current_session.lock().unwrap();
+        let Some(session) = current_session.take() else {
+            return Err(String::from("No current session"));
+        };
+
+        let answered = get_answered_questions(&mut *connection, &session.session_id)?;
+        let completed = session
+            .theme
+            .questions
+            .iter()
+            .filter(|question| question.options.as_ref().is_some_and(|o| !o.is_empty()))
+            .all(|question| answered.contains(&question.key));
+        let status = if completed {
+            SessionStatus::Completed
+        } else {
+            SessionStatus::Abandoned
+        };
+
+        end_session(&mut *connection, &session.session_id, status)?;
+        Ok(status)
+    }
+
+    /// Connect to every reader and start reading
+    ///
+    /// The tags of all readers end up in the same `TagsMap`, using the option slots of their connection.
+    /// If any reader fails, the readers that did connect are disconnected again, so we never read with only some.
     pub fn start_reading<R: tauri::Runtime>(
         &self,
-        hostname: String,
+        connections: Vec<ReaderConnection>,
         app_handle: AppHandle<R>,
     ) -> Result<(), ReaderError> {
-        let mut lock = self.reader.lock().unwrap();
-        // If the user refreshes we *might* already have a reader that is connected
-        if let Some(reader) = lock.take() {
+        // A reader is identified by its hostname, so it can't be used twice
+        let mut hostnames = BTreeSet::new();
+        if let Some(duplicate) = connections
+            .iter()
+            .find(|connection| !hostnames.insert(&connection.hostname))
+        {
+            return Err(ReaderError {
+                kind: ReaderErrorKind::IncorrectHostname(duplicate.hostname.clone()),
+                message: String::from("This reader was added more than once"),
+            });
+        }
+
+        let mut readers = self.readers.lock().unwrap();
+        // If the user refreshes we *might* already have readers that are connected
+        for (hostname, reader) in std::mem::take(&mut *readers) {
+            update_state(&app_handle, &hostname, ReaderState::Reconnecting);
             drop(reader);
         }
+        // Readers we reconnect keep their state until they report a new one
+        self.reader_states
+            .lock()
+            .unwrap()
+            .retain(|hostname, _| hostnames.contains(hostname));
 
-        let mut reader = match env::var("MOCK_RFID_READER") {
-            Ok(_) => Reader::MockReader(MockReader::new(hostname, app_handle.clone())?),
-            Err(_) => Reader::LLRPReader(LLRPReader::new(hostname, app_handle.clone())?),
-        };
-        reader.start_reading(app_handle)?;
-        *lock = Some(reader);
+        *self.diagnostics.lock().unwrap() = ReaderDiagnostics::default();
+        *self.tag_merger.lock().unwrap() = TagMerger::new(&connections);
+
+        for connection in connections {
+            let hostname = connection.hostname.clone();
+            let sink = tauri_sink(app_handle.clone(), hostname.clone());
+            let result = self
+                .reader_registry
+                .connect(&connection, self.reader_settings(), sink)
+                .and_then(|mut reader| {
+                    reader.start_reading()?;
+                    Ok(reader)
+                });
+
+            match result {
+                Ok(reader) => {
+                    readers.insert(hostname, reader);
+                }
+                Err(err) => {
+                    self.log_reader_event(
+                        &hostname,
+                        ReaderLogKind::Error,
+                        &format!("{}: {}", err.kind, err.message),
+                    );
+                    update_state(&app_handle, &hostname, ReaderState::Failed);
+                    for (hostname, reader) in std::mem::take(&mut *readers) {
+                        drop(reader);
+                        update_state(&app_handle, &hostname, ReaderState::Disconnected);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The combined state of all readers
+    pub fn reader_state(&self) -> ReaderState {
+        ReaderState::combine(self.reader_states.lock().unwrap().values())
+    }
+
+    pub fn reader_states(&self) -> BTreeMap<String, ReaderState> {
+        self.reader_states.lock().unwrap().clone()
+    }
+
+    pub fn reader_info(&self) -> BTreeMap<String, ReaderInfo> {
+        self.readers
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(hostname, reader)| Some((hostname.clone(), reader.info()?)))
+            .collect()
+    }
+
+    pub fn reader_diagnostics(&self) -> ReaderDiagnostics {
+        self.diagnostics.lock().unwrap().clone()
+    }
+
+    pub fn reader_settings(&self) -> ReaderSettings {
+        self.reader_settings.lock().unwrap().clone()
+    }
+
+    pub fn reader_backend(&self) -> String {
+        self.reader_backend.lock().unwrap().clone()
+    }
+
+    pub fn reader_backends(&self) -> Vec<&'static str> {
+        self.reader_registry.backends()
+    }
+
+    /// Store the backend we use for new connections, e.g. to run a training in demo mode
+    pub fn save_reader_backend(&self, backend: String) -> Result<(), CirclesError> {
+        if !self.reader_registry.contains(&backend) {
+            return Err(ReaderError {
+                kind: ReaderErrorKind::UnknownBackend(backend),
+                message: format!("Available backends are {:?}", self.reader_backends()),
+            }
+            .into());
+        }
+
+        let mut connection = self.database_connection.lock().unwrap();
+        save_setting(&mut *connection, READER_BACKEND_KEY, &backend).map_err(|message| {
+            GeneralError {
+                kind: GeneralErrorKind::Unknown,
+                message,
+            }
+        })?;
+        *self.reader_backend.lock().unwrap() = backend;
         Ok(())
     }
 
+    /// Connect to a single reader by hostname, using the saved backend
+    pub fn connection_for(&self, hostname: String) -> ReaderConnection {
+        ReaderConnection {
+            backend: self.reader_backend(),
+            ..ReaderConnection::new(hostname)
+        }
+    }
+
+    /// Store new reader settings and apply them to the connected reader (if any)
+    pub fn save_reader_settings(&self, settings: ReaderSettings) -> Result<(), CirclesError> {
+        {
+            // Readers log their state changes to the database, so we can't hold on to it while configuring them
+            let mut connection = self.database_connection.lock().unwrap();
+            save_setting(&mut *connection, READER_SETTINGS_KEY, &settings).map_err(|message| {
+                GeneralError {
+                    kind: GeneralErrorKind::Unknown,
+                    message,
+                }
+            })?;
+        }
+        *self.reader_settings.lock().unwrap() = settings.clone();
+
+        for reader in self.readers.lock().unwrap().values_mut() {
+            reader.configure(settings.clone())?;
+            reader.start_reading()?;
+        }
+        Ok(())
+    }
+
+    /// Measure which antennas of a reader see a test token placed in `circle`, for every power level
+    ///
+    /// This temporarily replaces the reader settings and blocks until all levels are measured, so it should not run
+    /// on the async runtime. The reader is only locked while we change its settings, not while we read.
+    /// Every measurement is also emitted as a `calibration-progress` event.
+    pub fn calibrate<R: tauri::Runtime>(
+        &self,
+        hostname: String,
+        token_id: String,
+        circle: u16,
+        app_handle: AppHandle<R>,
+    ) -> Result<CalibrationReport, ReaderError> {
+        let max_power_index = self.with_reader(&hostname, |reader| {
+            Ok(reader
+                .info()
+                .and_then(|info| info.max_power_index())
+                .unwrap_or(DEFAULT_MAX_POWER_INDEX))
+        })?;
+        self.calibration.lock().unwrap().start_circle(circle);
+        self.calibrating.store(true, Ordering::Relaxed);
+
+        let measure = || -> Result<(), ReaderError> {
+            for antenna in DEFAULT_ANTENNAS {
+                for power_index in Calibration::power_levels(max_power_index) {
+                    self.with_reader(&hostname, |reader| {
+                        reader.configure(ReaderSettings::single_antenna(antenna, power_index))?;
+                        self.reset_tags_map();
+                        reader.start_reading()
+                    })?;
+                    sleep(CALIBRATION_DWELL);
+                    self.with_reader(&hostname, |reader| reader.stop_reading(true))?;
+
+                    let sample = CalibrationSample {
+                        circle,
+                        antenna,
+                        power_index,
+                        rssi: self
+                            .tags_map
+                            .lock()
+                            .unwrap()
+                            .get(&token_id)
+                            .map(|tag| tag.strength),
+                    };
+                    let _ = app_handle.emit_all("calibration-progress", &sample);
+                    self.calibration.lock().unwrap().add_sample(sample);
+                }
+            }
+            Ok(())
+        };
+        let result = measure();
+        self.calibrating.store(false, Ordering::Relaxed);
+
+        // Always go back to our normal settings, even if the calibration failed
+        self.reset_tags_map();
+        self.with_reader(&hostname, |reader| {
+            reader.configure(self.reader_settings())?;
+            reader.start_reading()
+        })?;
+
+        result.map(|_| self.calibration_report())
+    }
+
+    /// Run `action` on a connected reader, holding the lock on the readers only while it runs
+    fn with_reader<T>(
+        &self,
+        hostname: &str,
+        action: impl FnOnce(&mut Reader) -> Result<T, ReaderError>,
+    ) -> Result<T, ReaderError> {
+        match self.readers.lock().unwrap().get_mut(hostname) {
+            Some(reader) => action(reader),
+            None => Err(ReaderError {
+                kind: ReaderErrorKind::NotConnected,
+                message: String::new(),
+            }),
+        }
+    }
+
+    pub fn calibration_report(&self) -> CalibrationReport {
+        self.calibration.lock().unwrap().report()
+    }
+
+    pub fn reset_calibration(&self) {
+        *self.calibration.lock().unwrap() = Calibration::default();
+    }
+
+    /// Save the recommended transmit power of the calibration
+    ///
+    /// Antennas that were not calibrated keep their current power.
+    pub fn save_calibration(&self) -> Result<ReaderSettings, CirclesError> {
+        let recommendation = self.calibration.lock().unwrap().recommend();
+        let mut settings = self.reader_settings();
+        for antenna in DEFAULT_ANTENNAS {
+            settings.transmit_power.entry(antenna).or_insert(0);
+        }
+        settings
+            .transmit_power
+            .extend(recommendation.transmit_power);
+
+        self.save_reader_settings(settings.clone())?;
+        Ok(settings)
+    }
+
     pub fn reset_tags_map(&self) {
+        self.tag_merger.lock().unwrap().reset();
         self.tags_map.lock().unwrap().reset()
     }
 
+    /// Open the vote on a step, starting with an empty `TagsMap`
+    pub fn open_step(&self) {
+        self.reset_tags_map();
+        *self.step_opened_at.lock().unwrap() = Some(now_micros());
+    }
+
+    /// Close the vote on the current step and save its results, together with when the vote opened and closed
+    ///
+    /// If the step was never opened, we save the results without these times.
+    pub fn close_step(&self, current_step: String) -> Result<(), String> {
+        let window = self.take_step_window();
+        self.save_step(current_step, window, false)
+    }
+
+    /// Close the vote on a step that was saved before, replacing its answers
+    pub fn redo_step(&self, current_step: String) -> Result<(), String> {
+        let window = self.take_step_window();
+        self.save_step(current_step, window, true)
+    }
+
     pub fn save_step_results(&self, current_step: String) -> Result<(), String> {
+        self.save_step(current_step, None, false)
+    }
+
+    /// Undo the step that was saved last in the current session, so voting on it can continue
+    ///
+    /// The `TagsMap` is restored to what was saved, and the vote keeps the time it originally opened.
+    pub fn undo_last_step(&self) -> Result<Option<UndoneStep>, String> {
+        let session_id = match self.current_session.lock().unwrap().as_ref() {
+            Some(session) => session.session_id,
+            None => return Err(String::from("No current session")),
+        };
+        let undone = {
+            let mut connection = self.database_connection.lock().unwrap();
+            undo_last_step(&mut *connection, &session_id)?
+        };
+        let Some((step, tags_map)) = undone else {
+            return Ok(None);
+        };
+
+        self.tag_merger.lock().unwrap().reset();
+        *self.tags_map.lock().unwrap() = tags_map.clone();
+        *self.step_opened_at.lock().unwrap() = step.opened_at.map(datetime_to_micros);
+        Ok(Some(UndoneStep {
+            question_key: step.question_key,
+            tags_map,
+        }))
+    }
+
+    fn take_step_window(&self) -> Option<StepWindow> {
+        self.step_opened_at
+            .lock()
+            .unwrap()
+            .take()
+            .map(|opened_at| StepWindow {
+                opened_at,
+                closed_at: now_micros(),
+            })
+    }
+
+    fn save_step(
+        &self,
+        current_step: String,
+        window: Option<StepWindow>,
+        redo: bool,
+    ) -> Result<(), String> {
+        let save = if redo { redo_step } else { save_step_results };
         let mut connection = self.database_connection.lock().unwrap();
         let current_session = self.current_session.lock().unwrap();
 
-        if current_session.is_some() {
-            save_step_results(
+        if let Some(session) = current_session.as_ref() {
+            let question = session.theme.find_question_by_key(&current_step);
+            save(
                 &mut *connection,
-                &current_session.as_ref().unwrap().session_id,
+                &session.session_id,
                 &current_step,
+                question.as_ref(),
+                window,
                 self.tags_map.lock().unwrap().clone(),
             )?;
 
@@ -126,17 +594,46 @@ impl GlobalState {
 
     pub fn stop_reading(&self, await_confirmation: bool) -> Result<(), ReaderError> {
         self.reset_tags_map();
-        let mut lock = self.reader.lock().unwrap();
-        if let Some(reader) = &mut *lock {
-            reader.stop_reading(await_confirmation)
-        } else {
-            Ok(())
+        for (hostname, reader) in self.readers.lock().unwrap().iter_mut() {
+            reader.stop_reading(await_confirmation)?;
+            self.log_reader_event(hostname, ReaderLogKind::Stopped, "");
         }
+        Ok(())
     }
 
-    pub fn drop_reader(&self) {
-        let mut lock = self.reader.lock().unwrap();
-        lock.take();
+    /// Add an event of a reader to the log of the current session
+    ///
+    /// The log is only there to help us afterwards, so failing to write it never stops the reader.
+    pub fn log_reader_event(&self, reader_id: &str, kind: ReaderLogKind, message: &str) {
+        let session_id = self
+            .current_session
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|session| session.session_id);
+        let mut connection = self.database_connection.lock().unwrap();
+        let _ = log_reader_event(
+            &mut *connection,
+            session_id,
+            reader_id,
+            &kind.to_string(),
+            message,
+        );
+    }
+
+    pub fn reader_events(&self, session_id: i32) -> Result<Vec<ReaderLogEntry>, String> {
+        let mut connection = self.database_connection.lock().unwrap();
+        get_reader_events(&mut *connection, &session_id)
+    }
+
+    /// Disconnect from every reader, and let the frontend know that they are disconnected
+    pub fn drop_reader<R: tauri::Runtime>(&self, app_handle: &AppHandle<R>) {
+        self.readers.lock().unwrap().clear();
+        let hostnames: Vec<String> = self.reader_states.lock().unwrap().keys().cloned().collect();
+        for hostname in hostnames {
+            update_state(app_handle, &hostname, ReaderState::Disconnected);
+        }
+        self.reader_states.lock().unwrap().clear();
     }
 }
 
@@ -163,7 +660,9 @@ mod tests {
         let state = GlobalState::build(":memory:".into()).unwrap();
         state.select_project("test".to_string()).unwrap();
 
-        assert!(state.start_session("theme-one".to_string()).is_ok())
+        assert!(state
+            .start_session("theme-one".to_string(), SessionMetadata::default())
+            .is_ok())
     }
 
     #[test]
@@ -171,13 +670,305 @@ mod tests {
         let state = GlobalState::build(":memory:".into()).unwrap();
         state.select_project("test".to_string()).unwrap();
 
-        assert!(state.start_session("theme-zero".to_string()).is_err())
+        assert!(state
+            .start_session("theme-zero".to_string(), SessionMetadata::default())
+            .is_err())
     }
 
     #[test]
     fn should_err_if_starting_session_before_project() {
         let state = GlobalState::build(":memory:".into()).unwrap();
 
-        assert!(state.start_session("theme-one".to_string()).is_err())
+        assert!(state
+            .start_session("theme-one".to_string(), SessionMetadata::default())
+            .is_err())
+    }
+
+    #[test]
+    fn should_reject_readers_that_are_added_twice() {
+        let state = GlobalState::build(":memory:".into()).unwrap();
+        let app = tauri::test::mock_app();
+        let connection = ReaderConnection::new(String::from("fx9600749620"));
+
+        let result = state.start_reading(vec![connection.clone(), connection], app.handle());
+
+        assert!(result.is_err_and(
+            |err| err.kind == ReaderErrorKind::IncorrectHostname(String::from("fx9600749620"))
+        ));
+        assert!(state.readers.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn should_err_if_calibrating_without_reader() {
+        let state = GlobalState::build(":memory:".into()).unwrap();
+        let app = tauri::test::mock_app();
+
+        let result = state.calibrate(
+            "fx9600749620".to_string(),
+            "abc123".to_string(),
+            1,
+            app.handle(),
+        );
+
+        assert!(result.is_err_and(|err| err.kind == ReaderErrorKind::NotConnected));
+    }
+
+    #[test]
+    fn should_save_calibration_for_all_antennas() {
+        let state = GlobalState::build(":memory:".into()).unwrap();
+        let app = tauri::test::mock_app();
+        state
+            .calibration
+            .lock()
+            .unwrap()
+            .add_sample(CalibrationSample {
+                circle: 1,
+                antenna: 1,
+                power_index: 16,
+                rssi: Some(-40),
+            });
+
+        let settings = state.save_calibration().unwrap();
+
+        assert_eq!(settings.transmit_power, [(1, 24), (2, 0), (3, 0)].into());
+        assert_eq!(state.reader_settings(), settings);
+    }
+
+    #[test]
+    fn should_persist_reader_backend() {
+        let state = GlobalState::build(":memory:".into()).unwrap();
+        assert_eq!(state.reader_backend(), DEFAULT_BACKEND);
+
+        state.save_reader_backend(String::from("mock")).unwrap();
+
+        assert_eq!(state.reader_backend(), "mock");
+        assert_eq!(
+            state.connection_for(String::from("demo")).backend,
+            String::from("mock")
+        );
+        let stored: Option<String> = get_setting(
+            &mut *state.database_connection.lock().unwrap(),
+            READER_BACKEND_KEY,
+        );
+        assert_eq!(stored, Some(String::from("mock")));
+    }
+
+    #[test]
+    fn should_end_and_abandon_sessions() {
+        use database::{models::Session, schema::sessions};
+
+        let state = GlobalState::build(":memory:".into()).unwrap();
+        state.select_project("test".to_string()).unwrap();
+        let first = state
+            .start_session("theme-one".to_string(), SessionMetadata::default())
+            .unwrap();
+        let second = state
+            .start_session("theme-one".to_string(), SessionMetadata::default())
+            .unwrap();
+
+        // Nothing was answered in the second session yet
+        assert_eq!(state.end_session(), Ok(SessionStatus::Abandoned));
+        assert!(state.end_session().is_err());
+
+        state
+            .start_session("theme-one".to_string(), SessionMetadata::default())
+            .unwrap();
+        state.save_step_results("question-one".to_string()).unwrap();
+        assert_eq!(state.end_session(), Ok(SessionStatus::Completed));
+
+        let mut connection = state.database_connection.lock().unwrap();
+        let sessions: Vec<Session> = sessions::table
+            .select(Session::as_select())
+            .load(&mut *connection)
+            .unwrap();
+        let statuses: Vec<(i32, SessionStatus)> = sessions
+            .iter()
+            .map(|session| (session.id, session.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                (first, SessionStatus::Abandoned),
+                (second, SessionStatus::Abandoned),
+                (second + 1, SessionStatus::Completed)
+            ]
+        );
+        assert!(sessions.iter().all(|session| session.ended_at.is_some()));
+    }
+
+    #[test]
+    fn should_save_voting_window_when_closing_step() {
+        use database::{models::Step, schema::steps};
+
+        let state = GlobalState::build(":memory:".into()).unwrap();
+        state.select_project("test".to_string()).unwrap();
+        state
+            .start_session("theme-one".to_string(), SessionMetadata::default())
+            .unwrap();
+
+        state.open_step();
+        state.close_step("question-one".to_string()).unwrap();
+        // Redoing a step that was never opened saves it without a voting window
+        state.redo_step("question-one".to_string()).unwrap();
+        // Closing a step that was never opened still saves its results
+        state.close_step("question-two".to_string()).unwrap();
+
+        let mut connection = state.database_connection.lock().unwrap();
+        let steps: Vec<Step> = steps::table
+            .select(Step::as_select())
+            .order(steps::id.asc())
+            .load(&mut *connection)
+            .unwrap();
+        assert_eq!(steps.len(), 2);
+        assert_eq!((steps[0].opened_at, steps[0].closed_at), (None, None));
+        assert_eq!((steps[1].opened_at, steps[1].closed_at), (None, None));
+        // The window of the first vote is kept with the answers it superseded
+        let revisions = database::get_step_revisions(&mut *connection, &steps[0].id).unwrap();
+        assert_eq!(revisions.len(), 1);
+        assert!(revisions[0]
+            .opened_at
+            .is_some_and(|opened_at| revisions[0].closed_at >= opened_at));
+    }
+
+    #[test]
+    fn should_edit_session_metadata() {
+        let state = GlobalState::build(":memory:".into()).unwrap();
+        state.select_project("test".to_string()).unwrap();
+        let metadata = SessionMetadata {
+            location: Some(String::from("Library")),
+            expected_participants: Some(25),
+            ..Default::default()
+        };
+        let session_id = state
+            .start_session("theme-one".to_string(), metadata.clone())
+            .unwrap();
+        assert_eq!(state.session_metadata(session_id), Ok(metadata));
+
+        let edited = SessionMetadata {
+            notes: Some(String::from("Fire drill halfway")),
+            ..Default::default()
+        };
+        assert_eq!(
+            state.update_session_metadata(session_id, edited.clone()),
+            Ok(edited.clone())
+        );
+        assert_eq!(state.session_metadata(session_id), Ok(edited));
+    }
+
+    #[test]
+    fn should_undo_last_step_and_continue_voting() {
+        let state = GlobalState::build(":memory:".into()).unwrap();
+        state.select_project("test".to_string()).unwrap();
+        assert!(state.undo_last_step().is_err());
+        state
+            .start_session("theme-one".to_string(), SessionMetadata::default())
+            .unwrap();
+        assert!(state.undo_last_step().unwrap().is_none());
+
+        state.open_step();
+        *state.tags_map.lock().unwrap() = TagsMap::random(3);
+        let saved = state.tags_map.lock().unwrap().values().len();
+        state.close_step("question-one".to_string()).unwrap();
+        state.open_step();
+
+        let undone = state.undo_last_step().unwrap().unwrap();
+        assert_eq!(undone.question_key, "question-one");
+        assert_eq!(undone.tags_map.values().len(), saved);
+        assert_eq!(state.tags_map.lock().unwrap().values().len(), saved);
+        assert!(state.step_opened_at.lock().unwrap().is_some());
+        assert!(state.undo_last_step().unwrap().is_none());
+    }
+
+    #[test]
+    fn should_resume_unfinished_session() {
+        let database =
+            std::env::temp_dir().join(format!("circles-resume-{}.db", std::process::id()));
+        let session_id = {
+            let state = GlobalState::build(database.clone()).unwrap();
+            state.select_project("test".to_string()).unwrap();
+            let session_id = state
+                .start_session("theme-one".to_string(), SessionMetadata::default())
+                .unwrap();
+            state.save_step_results("question-one".to_string()).unwrap();
+            session_id
+        };
+
+        // As if the app restarted during the session
+        let state = GlobalState::build(database.clone()).unwrap();
+        let resumable = state.resumable_session().unwrap().unwrap();
+        assert_eq!(
+            resumable,
+            ResumableSession {
+                session_id,
+                project_key: String::from("test"),
+                theme_key: String::from("theme-one"),
+                last_step: Some(String::from("question-one")),
+            }
+        );
+        assert!(state.resume_session(session_id).is_err());
+
+        state.select_project("test".to_string()).unwrap();
+        assert_eq!(state.resume_session(session_id), Ok(resumable));
+        state.save_step_results("question-one".to_string()).unwrap();
+        assert_eq!(
+            state
+                .current_session
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map(|s| s.session_id),
+            Some(session_id)
+        );
+        assert_eq!(state.end_session(), Ok(SessionStatus::Completed));
+        assert_eq!(state.resumable_session(), Ok(None));
+
+        drop(state);
+        std::fs::remove_file(database).unwrap();
+    }
+
+    #[test]
+    fn should_log_reader_events_for_current_session() {
+        let state = GlobalState::build(":memory:".into()).unwrap();
+        state.log_reader_event("fx9600749620", ReaderLogKind::State, "Connecting");
+        state.select_project("test".to_string()).unwrap();
+        let session_id = state
+            .start_session("theme-one".to_string(), SessionMetadata::default())
+            .unwrap();
+
+        state.log_reader_event("fx9600749620", ReaderLogKind::Stopped, "");
+
+        let events = state.reader_events(session_id).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, "Stopped");
+        assert_eq!(events[0].reader_id, "fx9600749620");
+    }
+
+    #[test]
+    fn should_reject_unknown_reader_backend() {
+        let state = GlobalState::build(":memory:".into()).unwrap();
+
+        let result = state.save_reader_backend(String::from("carrier-pigeon"));
+
+        assert!(result.is_err());
+        assert_eq!(state.reader_backend(), DEFAULT_BACKEND);
+    }
+
+    #[test]
+    fn should_be_disconnected_without_reader() {
+        let app = tauri::test::mock_app();
+        app.manage(GlobalState::build(":memory:".into()).unwrap());
+        let state = app.state::<GlobalState>();
+        assert_eq!(state.reader_state(), ReaderState::Disconnected);
+
+        state
+            .reader_states
+            .lock()
+            .unwrap()
+            .insert("fx9600749620".to_string(), ReaderState::Failed);
+        assert_eq!(state.reader_state(), ReaderState::Failed);
+        state.drop_reader(&app.handle());
+
+        assert_eq!(state.reader_state(), ReaderState::Disconnected);
+        assert!(state.reader_states.lock().unwrap().is_empty());
     }
 }
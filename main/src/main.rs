@@ -1,13 +1,24 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use circles::{error::CirclesError, export::export_project_data, projects::Project, GlobalState};
-use std::fs;
+use circles::{
+    database::models::{ReaderLogEntry, SessionMetadata, SessionStatus},
+    error::{CirclesError, GeneralError, GeneralErrorKind},
+    export::export_project_data,
+    projects::Project,
+    reader::{
+        calibration::CalibrationReport, ReaderConnection, ReaderDiagnostics, ReaderInfo,
+        ReaderSettings, ReaderState,
+    },
+    GlobalState, ResumableSession, UndoneStep,
+};
+use std::{collections::BTreeMap, fs};
 use tauri::Manager;
 
 #[tauri::command]
-async fn get_projects() -> Vec<Project> {
-    Project::build_all()
+async fn get_projects(state: tauri::State<'_, GlobalState>) -> Result<Vec<Project>, String> {
+    let mut connection = state.database_connection.lock().unwrap();
+    Project::all(&mut *connection)
 }
 
 #[tauri::command]
@@ -25,15 +36,152 @@ async fn select_project(
     app_handle: tauri::AppHandle,
     project_key: String,
     hostname: String,
+    backend: Option<String>,
+    readers: Option<Vec<ReaderConnection>>,
 ) -> Result<(), CirclesError> {
     state.select_project(project_key)?;
-    state.start_reading(hostname, app_handle)?;
+    if let Some(backend) = backend {
+        state.save_reader_backend(backend)?;
+    }
+    let connections = readers.unwrap_or_else(|| vec![state.connection_for(hostname)]);
+    state.start_reading(connections, app_handle)?;
     Ok(())
 }
 
 #[tauri::command]
-fn start_session(state: tauri::State<GlobalState>, theme_key: String) -> Result<i32, String> {
-    state.start_session(theme_key)
+fn get_reader_backend(state: tauri::State<GlobalState>) -> String {
+    state.reader_backend()
+}
+
+#[tauri::command]
+fn get_reader_backends(state: tauri::State<GlobalState>) -> Vec<&'static str> {
+    state.reader_backends()
+}
+
+#[tauri::command]
+fn get_reader_state(state: tauri::State<GlobalState>) -> ReaderState {
+    state.reader_state()
+}
+
+#[tauri::command]
+fn get_reader_states(state: tauri::State<GlobalState>) -> BTreeMap<String, ReaderState> {
+    state.reader_states()
+}
+
+#[tauri::command]
+fn get_reader_info(state: tauri::State<GlobalState>) -> BTreeMap<String, ReaderInfo> {
+    state.reader_info()
+}
+
+#[tauri::command]
+fn get_reader_settings(state: tauri::State<GlobalState>) -> ReaderSettings {
+    state.reader_settings()
+}
+
+#[tauri::command]
+async fn save_reader_settings(
+    state: tauri::State<'_, GlobalState>,
+    settings: ReaderSettings,
+) -> Result<(), CirclesError> {
+    state.save_reader_settings(settings)
+}
+
+#[tauri::command]
+fn get_reader_diagnostics(state: tauri::State<GlobalState>) -> ReaderDiagnostics {
+    state.reader_diagnostics()
+}
+
+#[tauri::command]
+fn get_reader_events(
+    state: tauri::State<GlobalState>,
+    session_id: i32,
+) -> Result<Vec<ReaderLogEntry>, String> {
+    state.reader_events(session_id)
+}
+
+#[tauri::command]
+async fn calibrate_antennas(
+    app_handle: tauri::AppHandle,
+    hostname: String,
+    token_id: String,
+    circle: u16,
+) -> Result<CalibrationReport, CirclesError> {
+    // Calibrating blocks for a while, so it shouldn't hold up the async runtime
+    let handle = app_handle.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        handle
+            .state::<GlobalState>()
+            .calibrate(hostname, token_id, circle, app_handle)
+    })
+    .await
+    .map_err(|err| GeneralError {
+        kind: GeneralErrorKind::Unknown,
+        message: err.to_string(),
+    })?
+    .map_err(CirclesError::from)
+}
+
+#[tauri::command]
+fn get_calibration(state: tauri::State<GlobalState>) -> CalibrationReport {
+    state.calibration_report()
+}
+
+#[tauri::command]
+fn reset_calibration(state: tauri::State<GlobalState>) {
+    state.reset_calibration()
+}
+
+#[tauri::command]
+async fn save_calibration(
+    state: tauri::State<'_, GlobalState>,
+) -> Result<ReaderSettings, CirclesError> {
+    state.save_calibration()
+}
+
+#[tauri::command]
+fn start_session(
+    state: tauri::State<GlobalState>,
+    theme_key: String,
+    metadata: Option<SessionMetadata>,
+) -> Result<i32, String> {
+    state.start_session(theme_key, metadata.unwrap_or_default())
+}
+
+#[tauri::command]
+fn update_session_metadata(
+    state: tauri::State<GlobalState>,
+    session_id: i32,
+    metadata: SessionMetadata,
+) -> Result<SessionMetadata, String> {
+    state.update_session_metadata(session_id, metadata)
+}
+
+#[tauri::command]
+fn get_session_metadata(
+    state: tauri::State<GlobalState>,
+    session_id: i32,
+) -> Result<SessionMetadata, String> {
+    state.session_metadata(session_id)
+}
+
+#[tauri::command]
+fn get_resumable_session(
+    state: tauri::State<GlobalState>,
+) -> Result<Option<ResumableSession>, String> {
+    state.resumable_session()
+}
+
+#[tauri::command]
+fn resume_session(
+    state: tauri::State<GlobalState>,
+    session_id: i32,
+) -> Result<ResumableSession, String> {
+    state.resume_session(session_id)
+}
+
+#[tauri::command]
+fn end_session(state: tauri::State<GlobalState>) -> Result<SessionStatus, String> {
+    state.end_session()
 }
 
 #[tauri::command]
@@ -41,6 +189,34 @@ fn reset_tags_map(state: tauri::State<GlobalState>) {
     state.reset_tags_map()
 }
 
+#[tauri::command]
+fn open_step(state: tauri::State<GlobalState>) {
+    state.open_step()
+}
+
+#[tauri::command]
+async fn close_step(
+    state: tauri::State<'_, GlobalState>,
+    current_step: String,
+) -> Result<(), String> {
+    state.close_step(current_step)
+}
+
+#[tauri::command]
+async fn redo_step(
+    state: tauri::State<'_, GlobalState>,
+    current_step: String,
+) -> Result<(), String> {
+    state.redo_step(current_step)
+}
+
+#[tauri::command]
+async fn undo_last_step(
+    state: tauri::State<'_, GlobalState>,
+) -> Result<Option<UndoneStep>, String> {
+    state.undo_last_step()
+}
+
 #[tauri::command]
 async fn save_step_results(
     state: tauri::State<'_, GlobalState>,
@@ -50,8 +226,8 @@ async fn save_step_results(
 }
 
 #[tauri::command]
-fn close_connection(state: tauri::State<GlobalState>) -> () {
-    state.drop_reader();
+fn close_connection(app_handle: tauri::AppHandle, state: tauri::State<GlobalState>) -> () {
+    state.drop_reader(&app_handle);
 }
 
 #[tauri::command]
@@ -60,8 +236,6 @@ async fn save_export(
     filepath: String,
     project_key: String,
 ) -> Result<(), CirclesError> {
-    // NOTE: This allows any arbitrary project_key, but will simply not find results if the project key does not exists
-    // Once we move projects to the database, we'll solve this in a more fundamental way
     let mut connection = state.database_connection.lock().unwrap();
     export_project_data(&mut *connection, filepath, project_key)?;
     Ok(())
@@ -87,14 +261,36 @@ fn main() {
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
+            calibrate_antennas,
             close_connection,
+            get_calibration,
             get_projects,
+            get_reader_backend,
+            get_reader_backends,
+            get_reader_diagnostics,
+            get_reader_events,
+            get_reader_info,
+            get_reader_settings,
+            get_reader_state,
+            get_reader_states,
             import_project,
+            reset_calibration,
             reset_tags_map,
+            save_calibration,
             save_export,
+            save_reader_settings,
             save_step_results,
+            open_step,
+            close_step,
+            redo_step,
+            undo_last_step,
             select_project,
             start_session,
+            update_session_metadata,
+            get_session_metadata,
+            end_session,
+            get_resumable_session,
+            resume_session,
         ])
         .build(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -103,7 +299,7 @@ fn main() {
     app.run(|app_handle, event| match event {
         tauri::RunEvent::Exit { .. } => {
             let state = app_handle.state::<GlobalState>();
-            state.drop_reader();
+            state.drop_reader(app_handle);
         }
         _ => {}
     })
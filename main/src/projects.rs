@@ -1,5 +1,9 @@
+use diesel::SqliteConnection;
 use include_dir::{include_dir, Dir, File};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::database::{get_project, get_projects};
 
 // NOTE: This path is relative to the Cargo root
 const PROJECTS_DIR: Dir = include_dir!("../projects");
@@ -13,6 +17,9 @@ pub struct Project {
     pub themes: Vec<Theme>,
     pub name: TranslatedProperty,
     pub available_languages: Vec<String>,
+    /// The contents of the project file this project was parsed from
+    #[serde(skip)]
+    pub source: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -41,7 +48,7 @@ pub struct QuestionOption {
     pub correct: Option<bool>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct TranslatedProperty {
     pub nl: Option<String>,
@@ -51,8 +58,18 @@ pub struct TranslatedProperty {
 }
 
 impl Project {
+    /// Read a project from its project file. The projects in the database are seeded from these.
     pub fn build(file: &File) -> Project {
-        serde_json::from_str(file.contents_utf8().unwrap()).expect("error while reading")
+        Self::parse(file.contents_utf8().unwrap()).expect("error while reading")
+    }
+
+    /// Parse the contents of a project file, keeping those contents as its source
+    pub fn parse(source: &str) -> serde_json::Result<Project> {
+        let project: Project = serde_json::from_str(source)?;
+        Ok(Project {
+            source: Some(source.to_string()),
+            ..project
+        })
     }
 
     pub fn build_all() -> Vec<Project> {
@@ -71,15 +88,33 @@ impl Project {
         result
     }
 
-    pub fn find_by_key(project_key: &str) -> Option<Project> {
-        Project::build_all()
-            .iter()
-            .find(|project| project.key == project_key)
-            .cloned()
+    pub fn find_by_key(connection: &mut SqliteConnection, project_key: &str) -> Option<Project> {
+        get_project(connection, project_key)
+    }
+
+    pub fn all(connection: &mut SqliteConnection) -> Result<Vec<Project>, String> {
+        get_projects(connection)
     }
 }
 
 impl Project {
+    /// The definition of this project as JSON, together with its SHA-256 hash (in hex)
+    ///
+    /// We use the project file itself, so any change to it results in another hash, including changes to properties
+    /// we don't map. Only projects without a source are serialized instead.
+    pub fn snapshot(&self) -> (String, String) {
+        let content = match &self.source {
+            Some(source) => source.clone(),
+            // We can unwrap, since our project only consists of strings, lists and structs
+            None => serde_json::to_string(self).unwrap(),
+        };
+        let hash = Sha256::digest(content.as_bytes())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        (hash, content)
+    }
+
     pub fn find_theme_by_key(&self, key: &str) -> Option<Theme> {
         let theme_key: String = key.into();
         self.themes
@@ -100,7 +135,13 @@ impl Question {
         self.options
             .clone()
             // Our antenna's use 1-based indexing
-            .and_then(|opts| opts.get(index - 1).cloned())
+            .and_then(|opts| opts.get(index.checked_sub(1)?).cloned())
+    }
+
+    pub fn find_option_by_key(&self, key: &str) -> Option<QuestionOption> {
+        self.options
+            .as_ref()
+            .and_then(|opts| opts.iter().find(|option| option.key == key).cloned())
     }
 }
 
@@ -119,6 +160,13 @@ impl TranslatedProperty {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::database::setup_database;
+    use std::path::PathBuf;
+
+    fn find_test_project() -> Project {
+        let mut connection = setup_database(&PathBuf::from(":memory:")).unwrap();
+        Project::find_by_key(&mut connection, "test").unwrap()
+    }
 
     #[test]
     fn can_parse_project() {
@@ -148,9 +196,57 @@ mod tests {
 
     #[test]
     fn should_be_able_to_find_project_by_key() {
-        let project = Project::find_by_key("test");
+        let mut connection = setup_database(&PathBuf::from(":memory:")).unwrap();
+        let project = Project::find_by_key(&mut connection, "test");
 
         assert!(project.is_some());
         assert_eq!(project.unwrap().key, "test");
+        assert!(Project::find_by_key(&mut connection, "unknown").is_none());
+    }
+
+    #[test]
+    fn should_load_the_same_project_as_its_file() {
+        let file = PROJECTS_DIR.get_file("test.json").unwrap();
+        let project = find_test_project();
+
+        assert_eq!(project.snapshot().0, Project::build(&file).snapshot().0);
+        // Without its source, the project we stored is still the same as the one in the file
+        let stored = Project {
+            source: None,
+            ..project
+        };
+        let parsed = Project {
+            source: None,
+            ..Project::build(&file)
+        };
+        assert_eq!(stored.snapshot().0, parsed.snapshot().0);
+    }
+
+    #[test]
+    fn should_change_snapshot_hash_when_project_changes() {
+        let mut project = find_test_project();
+        let (hash, content) = project.snapshot();
+
+        assert_eq!(hash.len(), 64);
+        assert_eq!(project.snapshot().0, hash);
+        let restored = Project::parse(&content).unwrap();
+        assert_eq!(restored.snapshot().0, hash);
+
+        project.source = content
+            .replace("How is your day going?", "How was your day?")
+            .into();
+        assert_ne!(project.snapshot().0, hash);
+    }
+
+    #[test]
+    fn should_find_option_by_antenna_and_key() {
+        let question = find_test_project().themes[0].questions[0].clone();
+
+        let option = question.find_option_by_antenna_index(2).unwrap();
+
+        assert_eq!(option.key, "question-one-option-two");
+        assert!(question.find_option_by_key(&option.key).is_some());
+        assert!(question.find_option_by_antenna_index(0).is_none());
+        assert!(question.find_option_by_antenna_index(3).is_none());
     }
 }
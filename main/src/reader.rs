@@ -1,136 +1,152 @@
+pub mod calibration;
+pub mod diagnostics;
 pub mod error;
+pub mod hid_reader;
+pub mod info;
+mod llrp_connection;
 mod llrp_reader;
+pub mod log;
+pub mod merge;
 pub mod messages;
 mod mock_reader;
+pub mod registry;
 mod rospec;
+pub mod scenario;
+pub mod serial_reader;
+pub mod settings;
+pub mod sink;
+mod state;
+pub mod tls;
 
+pub use diagnostics::ReaderDiagnostics;
 pub use error::{ReaderError, ReaderErrorKind};
-use llrp::messages::Message;
+pub use hid_reader::HidReader;
+pub use info::ReaderInfo;
 pub use llrp_reader::LLRPReader;
+pub use log::ReaderLogKind;
+pub use merge::{ReaderConnection, TagMerger};
 pub use mock_reader::MockReader;
+pub use registry::ReaderRegistry;
+pub use serial_reader::SerialReader;
+pub use settings::ReaderSettings;
+pub use sink::{ReaderEvent, ReaderSink};
+pub use state::{ReaderState, ReaderStateChange};
 use std::{
-    net::TcpStream,
-    sync::mpsc::{channel, Sender},
+    collections::BTreeMap,
+    sync::{atomic::Ordering, Mutex},
     time::{Duration, Instant},
 };
 use tauri::{AppHandle, Manager};
 
-use crate::{error::CirclesError, GlobalState};
-
-use self::messages::handle_new_message;
+use crate::{error::CirclesError, tags::Tag, GlobalState};
 
 const DEFAULT_ROSPEC_ID: u32 = 1234;
 const REFRESH_INTERVAL: u32 = 125;
 const RECV_TIMEOUT: Duration = Duration::from_millis(100);
 
-#[derive(Debug)]
-pub enum Reader {
-    LLRPReader(LLRPReader),
-    MockReader(MockReader),
-}
-
-impl Reader {
-    pub fn start_reading<R: tauri::Runtime>(
-        &mut self,
-        app_handle: AppHandle<R>,
-    ) -> Result<(), ReaderError> {
-        match self {
-            Reader::LLRPReader(reader) => reader.start_reading(app_handle),
-            Reader::MockReader(reader) => reader.start_reading(app_handle),
-        }
-    }
-
-    pub fn stop_reading(&mut self, await_confirmation: bool) -> Result<(), ReaderError> {
-        match self {
-            Reader::LLRPReader(reader) => reader.stop_reading(await_confirmation),
-            Reader::MockReader(reader) => reader.stop_reading(await_confirmation),
-        }
-    }
-}
+/// A connected reader, of any backend
+pub type Reader = Box<dyn ReaderProtocol>;
 
-pub trait ReaderProtocol {
-    fn new<R: tauri::Runtime>(
-        hostname: String,
-        app_handle: AppHandle<R>,
-    ) -> Result<Self, ReaderError>
-    where
-        Self: Sized;
-    fn start_reading<R: tauri::Runtime>(
-        &mut self,
-        app_handle: AppHandle<R>,
-    ) -> Result<(), ReaderError>;
+/// The interface every reader backend implements
+///
+/// Backends are created through the `ReaderRegistry` and report everything through their `ReaderSink`.
+pub trait ReaderProtocol: Send + std::fmt::Debug {
+    fn start_reading(&mut self) -> Result<(), ReaderError>;
+    fn configure(&mut self, settings: ReaderSettings) -> Result<(), ReaderError>;
+    fn info(&self) -> Option<ReaderInfo>;
     fn stop_reading(&mut self, await_confirmation: bool) -> Result<(), ReaderError>;
 }
 
-pub fn handle_reader_input<R: tauri::Runtime>(
-    stream: TcpStream,
-    app_handle: AppHandle<R>,
-) -> tauri::async_runtime::JoinHandle<()> {
-    tauri::async_runtime::spawn(async move {
-        let (tx, rx) = channel::<Message>();
-        let tags_map = app_handle.state::<GlobalState>().tags_map.clone();
-        let mut last_update = Instant::now();
-        let mut last_alive = Instant::now();
-        let update_interval = Duration::from_millis(REFRESH_INTERVAL.into());
-        let alive_interval = Duration::from_millis((REFRESH_INTERVAL * 10).into());
-
-        // Since reading from a TcpStream is blocking, we do this in a subthread.
-        // The messages get send to this thread, so we loop regardless of new messages.
-        receive_messages(stream.try_clone().unwrap(), tx);
-        loop {
-            if let Ok(message) = rx.recv_timeout(RECV_TIMEOUT) {
-                let tags = handle_new_message(message, &stream);
-                tags_map.lock().unwrap().add_tags(tags);
-                last_alive = Instant::now();
-            }
+/// Create a sink that passes the events of a reader on to our `GlobalState` and the frontend
+pub fn tauri_sink<R: tauri::Runtime>(app_handle: AppHandle<R>, reader_id: String) -> ReaderSink {
+    let update_interval = Duration::from_millis(REFRESH_INTERVAL.into());
+    let last_update = Mutex::new(Instant::now());
+    ReaderSink::new(reader_id, move |reader_id, event| match event {
+        ReaderEvent::Tags(tags) => {
+            add_tags(&app_handle, reader_id, tags);
 
-            if last_update.elapsed() > update_interval {
+            let mut last_update = last_update.lock().unwrap();
+            if last_update.elapsed() >= update_interval {
                 // Update the frontend with a new map.
                 // We clone the map inside our mutex, since we don't care about any changes while we are sending this event
-                app_handle
-                    .emit_all("updated-tags", tags_map.lock().unwrap().clone())
-                    .unwrap();
-                last_update = Instant::now();
-            }
-
-            if last_alive.elapsed() > alive_interval {
-                // If we are not alive for our interval, we assume the connection has failed
-                // In that case we break from our loop, so an error is sent.
-                send_error_to_frontend(
-                    app_handle,
-                    ReaderError {
-                        kind: ReaderErrorKind::LostConnection,
-                        message: String::from(""),
-                    }
-                    .into(),
-                );
-                break;
+                let tags_map = app_handle.state::<GlobalState>().tags_map.clone();
+                let _ = app_handle.emit_all("updated-tags", tags_map.lock().unwrap().clone());
+                *last_update = Instant::now();
             }
         }
+        ReaderEvent::State(state) => update_state(&app_handle, reader_id, state),
+        ReaderEvent::Error(error) => {
+            app_handle.state::<GlobalState>().log_reader_event(
+                reader_id,
+                ReaderLogKind::Error,
+                &format!("{}: {}", error.kind, error.message),
+            );
+            send_error_to_frontend(&app_handle, error.into())
+        }
+        ReaderEvent::Antenna { antenna, connected } => {
+            let status = if connected {
+                "connected"
+            } else {
+                "disconnected"
+            };
+            app_handle.state::<GlobalState>().log_reader_event(
+                reader_id,
+                ReaderLogKind::Antenna,
+                &format!("Antenna {} {}", antenna, status),
+            );
+        }
     })
 }
 
-fn send_error_to_frontend<R: tauri::Runtime>(app_handle: AppHandle<R>, error: CirclesError) {
+/// Add new reads of a reader to our `TagsMap`
+///
+/// Reads that are weaker than the minimum RSSI of their option slot are dropped first, so weak cross-reads
+/// from a neighbouring circle never end up in our results. While calibrating we keep every read, since we want to
+/// measure the weak reads as well.
+/// Afterwards the antennas are converted into option slots, which resolves conflicts between readers.
+pub fn add_tags<R: tauri::Runtime>(app_handle: &AppHandle<R>, reader_id: &str, tags: Vec<Tag>) {
+    let state = app_handle.state::<GlobalState>();
+    let min_rssi = match state.calibrating.load(Ordering::Relaxed) {
+        true => BTreeMap::new(),
+        false => state.reader_settings.lock().unwrap().min_rssi.clone(),
+    };
+    let mut tag_merger = state.tag_merger.lock().unwrap();
+    let tags = state
+        .diagnostics
+        .lock()
+        .unwrap()
+        .filter_tags(tags, &min_rssi, |antenna| {
+            tag_merger.slot(reader_id, antenna)
+        });
+    let tags = tag_merger.merge(reader_id, tags);
+    state.tags_map.lock().unwrap().add_tags(tags);
+}
+
+/// Keep track of the new state of a reader, log it and let the frontend know
+pub fn update_state<R: tauri::Runtime>(
+    app_handle: &AppHandle<R>,
+    reader_id: &str,
+    state: ReaderState,
+) {
+    let global_state = app_handle.state::<GlobalState>();
+    global_state.log_reader_event(reader_id, ReaderLogKind::State, &format!("{:?}", state));
+    global_state
+        .reader_states
+        .lock()
+        .unwrap()
+        .insert(reader_id.to_string(), state);
+    // We always ignore the output of this event, since we don't care if this fails.
+    let _ = app_handle.emit_all(
+        "reader-state",
+        ReaderStateChange {
+            reader: reader_id.to_string(),
+            state,
+        },
+    );
+}
+
+fn send_error_to_frontend<R: tauri::Runtime>(app_handle: &AppHandle<R>, error: CirclesError) {
     app_handle
         .emit_all("error", error)
         .expect("Should be able to emit to app_handle");
 }
-
-fn receive_messages(stream: TcpStream, sender: Sender<Message>) -> std::thread::JoinHandle<()> {
-    std::thread::spawn(move || {
-        while let Ok(message) = llrp::read_message(&stream) {
-            let res = match message.to_dynamic_message() {
-                Ok(m) => sender.send(m),
-                Err(err) => Ok({
-                    #[cfg(debug_assertions)]
-                    println!("Could not decode message. {}", err)
-                }),
-            };
-            // If sending fails, this probably means that our parent-thread has quit as well.
-            // In that case we just stop here and let this thread finish.
-            if res.is_err() {
-                break;
-            }
-        }
-    })
-}
@@ -0,0 +1,161 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use super::settings::ReaderSettings;
+
+/// How long we read at every power level
+pub const CALIBRATION_DWELL: Duration = Duration::from_millis(750);
+/// The highest power index we try if the reader did not tell us its transmit power table
+pub const DEFAULT_MAX_POWER_INDEX: u16 = 192;
+/// The difference in power index between two steps of the calibration
+const POWER_STEP: u16 = 16;
+/// The extra power we add on top of the lowest power that was able to see the token
+const POWER_MARGIN: u16 = 8;
+
+/// A single measurement of the calibration
+///
+/// The test token is placed in `circle`, while we only read with `antenna` at the given power index.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct CalibrationSample {
+    pub circle: u16,
+    pub antenna: u16,
+    pub power_index: u16,
+    /// The RSSI of the test token, if the antenna saw it
+    pub rssi: Option<i8>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CalibrationReport {
+    pub samples: Vec<CalibrationSample>,
+    pub recommendation: ReaderSettings,
+}
+
+/// Keeps track of the measurements made while calibrating the antennas
+///
+/// The facilitator places a test token in every circle in turn. For every circle we step through the power levels
+/// of every antenna, so we learn the power an antenna needs to see its own circle and the power at which it starts to
+/// see the neighbouring circles.
+#[derive(Debug, Default)]
+pub struct Calibration {
+    samples: Vec<CalibrationSample>,
+}
+
+impl Calibration {
+    /// The power levels we try, going from low to high
+    ///
+    /// LLRP numbers the entries of the transmit power table from 1, so that is the lowest level.
+    pub fn power_levels(max_power_index: u16) -> Vec<u16> {
+        let mut levels: Vec<u16> = (1..=max_power_index).step_by(POWER_STEP.into()).collect();
+        if levels.last() != Some(&max_power_index) {
+            levels.push(max_power_index);
+        }
+        levels
+    }
+
+    /// Start measuring a circle from scratch, dropping earlier measurements of this circle
+    pub fn start_circle(&mut self, circle: u16) {
+        self.samples.retain(|sample| sample.circle != circle);
+    }
+
+    pub fn add_sample(&mut self, sample: CalibrationSample) {
+        self.samples.push(sample);
+    }
+
+    pub fn report(&self) -> CalibrationReport {
+        CalibrationReport {
+            samples: self.samples.clone(),
+            recommendation: self.recommend(),
+        }
+    }
+
+    /// Recommend a transmit power for every antenna we measured in its own circle
+    ///
+    /// We use the lowest power that saw the token in its own circle (plus a small margin),
+    /// but stay below the lowest power that saw a token in another circle.
+    /// If those two conflict, we prefer seeing our own circle.
+    pub fn recommend(&self) -> ReaderSettings {
+        let mut needed: BTreeMap<u16, u16> = BTreeMap::new();
+        let mut cross_read: BTreeMap<u16, u16> = BTreeMap::new();
+
+        for sample in self.samples.iter().filter(|s| s.rssi.is_some()) {
+            let map = match sample.circle == sample.antenna {
+                true => &mut needed,
+                false => &mut cross_read,
+            };
+            map.entry(sample.antenna)
+                .and_modify(|power| *power = (*power).min(sample.power_index))
+                .or_insert(sample.power_index);
+        }
+
+        let transmit_power = needed
+            .into_iter()
+            .map(|(antenna, needed)| {
+                let mut power = needed.saturating_add(POWER_MARGIN);
+                if let Some(&cross_read) = cross_read.get(&antenna) {
+                    power = power.min(cross_read.saturating_sub(1)).max(needed);
+                }
+                (antenna, power)
+            })
+            .collect();
+
+        ReaderSettings {
+            transmit_power,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(circle: u16, antenna: u16, power_index: u16, seen: bool) -> CalibrationSample {
+        CalibrationSample {
+            circle,
+            antenna,
+            power_index,
+            rssi: seen.then_some(-50),
+        }
+    }
+
+    #[test]
+    fn should_include_max_power_level() {
+        assert_eq!(Calibration::power_levels(40), vec![1, 17, 33, 40]);
+        assert_eq!(Calibration::power_levels(33), vec![1, 17, 33]);
+    }
+
+    #[test]
+    fn should_recommend_lowest_power_that_sees_own_circle() {
+        let mut calibration = Calibration::default();
+        calibration.add_sample(sample(1, 1, 0, false));
+        calibration.add_sample(sample(1, 1, 16, true));
+        calibration.add_sample(sample(1, 1, 32, true));
+
+        let recommendation = calibration.recommend();
+
+        assert_eq!(recommendation.transmit_power, [(1, 24)].into());
+    }
+
+    #[test]
+    fn should_stay_below_cross_reads() {
+        let mut calibration = Calibration::default();
+        calibration.add_sample(sample(2, 2, 16, true));
+        // Antenna 2 starts seeing the token in circle 1 at power 20
+        calibration.add_sample(sample(1, 2, 16, false));
+        calibration.add_sample(sample(1, 2, 20, true));
+
+        let recommendation = calibration.recommend();
+
+        assert_eq!(recommendation.transmit_power, [(2, 19)].into());
+    }
+
+    #[test]
+    fn should_replace_samples_when_measuring_circle_again() {
+        let mut calibration = Calibration::default();
+        calibration.add_sample(sample(1, 1, 0, true));
+        calibration.add_sample(sample(2, 2, 0, true));
+
+        calibration.start_circle(1);
+
+        assert_eq!(calibration.report().samples, vec![sample(2, 2, 0, true)]);
+    }
+}
@@ -0,0 +1,98 @@
+use std::collections::BTreeMap;
+
+use crate::tags::Tag;
+
+/// Counters that help us understand what the reader pipeline is doing
+///
+/// These are reset every time we (re)connect to a reader.
+#[derive(Debug, Default, Clone, PartialEq, serde::Serialize)]
+pub struct ReaderDiagnostics {
+    /// Reads that were added to our `TagsMap`
+    pub accepted_reads: u64,
+    /// Reads that were weaker than the minimum RSSI of their option slot, per option slot
+    pub rejected_reads: BTreeMap<u16, u64>,
+}
+
+impl ReaderDiagnostics {
+    /// Drop every tag that is weaker than the minimum RSSI of the option slot it was read in
+    ///
+    /// Multiple readers use the same antenna numbers, so `slot` tells us which option slot an antenna of this reader
+    /// covers. Slots without a minimum accept every tag.
+    pub fn filter_tags(
+        &mut self,
+        tags: Vec<Tag>,
+        min_rssi: &BTreeMap<u16, i8>,
+        slot: impl Fn(u16) -> Option<u16>,
+    ) -> Vec<Tag> {
+        tags.into_iter()
+            .filter(|tag| {
+                let slot = slot(tag.antenna);
+                match slot.and_then(|slot| min_rssi.get(&slot).map(|&min| (slot, min))) {
+                    Some((slot, min)) if tag.strength < min => {
+                        *self.rejected_reads.entry(slot).or_default() += 1;
+                        false
+                    }
+                    _ => {
+                        self.accepted_reads += 1;
+                        true
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(antenna: u16, strength: i8) -> Tag {
+        Tag::new(String::from("abc123"), antenna, strength)
+    }
+
+    #[test]
+    fn should_reject_weak_reads_per_slot() {
+        let mut diagnostics = ReaderDiagnostics::default();
+        let min_rssi = BTreeMap::from([(1, -50), (2, -60)]);
+
+        let tags = diagnostics.filter_tags(
+            vec![
+                tag(1, -55),
+                tag(1, -45),
+                tag(2, -55),
+                tag(2, -65),
+                tag(3, -75),
+            ],
+            &min_rssi,
+            Some,
+        );
+
+        assert_eq!(tags.len(), 3);
+        assert_eq!(diagnostics.accepted_reads, 3);
+        assert_eq!(diagnostics.rejected_reads, BTreeMap::from([(1, 1), (2, 1)]));
+    }
+
+    #[test]
+    fn should_accept_reads_at_the_threshold() {
+        let mut diagnostics = ReaderDiagnostics::default();
+        let min_rssi = BTreeMap::from([(1, -50)]);
+
+        let tags = diagnostics.filter_tags(vec![tag(1, -50)], &min_rssi, Some);
+
+        assert_eq!(tags.len(), 1);
+        assert!(diagnostics.rejected_reads.is_empty());
+    }
+
+    #[test]
+    fn should_use_minimum_of_the_slot_of_an_antenna() {
+        let mut diagnostics = ReaderDiagnostics::default();
+        let min_rssi = BTreeMap::from([(1, -50), (4, -70)]);
+
+        // This reader covers slot 4 with its first antenna
+        let tags =
+            diagnostics.filter_tags(vec![tag(1, -60)], &min_rssi, |antenna| Some(antenna + 3));
+
+        assert_eq!(tags.len(), 1);
+        assert!(diagnostics.rejected_reads.is_empty());
+    }
+}
@@ -4,7 +4,13 @@ use std::fmt::{Display, Formatter};
 pub enum ReaderErrorKind {
     IncorrectHostname(String),
     CouldNotConnect(String),
+    UnknownBackend(String),
     LostConnection,
+    NotConnected,
+    Unsupported,
+    InvalidParameter,
+    InvalidArgument,
+    DeviceError,
     Unknown,
 }
 
@@ -27,7 +33,33 @@ impl Display for ReaderError {
                 "Could not connect to hostname {}. Original error: {}",
                 hostname, self.message
             ),
+            ReaderErrorKind::UnknownBackend(backend) => write!(
+                f,
+                "There is no reader backend called {}. {}",
+                backend, self.message
+            ),
             ReaderErrorKind::LostConnection => write!(f, "Connection with the reader was lost!"),
+            ReaderErrorKind::NotConnected => write!(f, "There is no reader connected."),
+            ReaderErrorKind::Unsupported => write!(
+                f,
+                "The reader does not support this request. Reader said: {}",
+                self.message
+            ),
+            ReaderErrorKind::InvalidParameter => write!(
+                f,
+                "The reader rejected a parameter in our request. Reader said: {}",
+                self.message
+            ),
+            ReaderErrorKind::InvalidArgument => write!(
+                f,
+                "The reader could not execute our request in its current state. Reader said: {}",
+                self.message
+            ),
+            ReaderErrorKind::DeviceError => write!(
+                f,
+                "The reader reported an internal error. Reader said: {}",
+                self.message
+            ),
             ReaderErrorKind::Unknown => write!(
                 f,
                 "Encountered an unexpected error in the reader. Message: {}",
@@ -37,13 +69,20 @@ impl Display for ReaderError {
     }
 }
 
-impl ToString for ReaderErrorKind {
-    fn to_string(&self) -> String {
-        match self {
-            ReaderErrorKind::IncorrectHostname(_) => String::from("IncorrectHostname"),
-            ReaderErrorKind::CouldNotConnect(_) => String::from("CouldNotConnect"),
-            ReaderErrorKind::LostConnection => String::from("LostConnection"),
-            ReaderErrorKind::Unknown => String::from("Unknown"),
-        }
+impl Display for ReaderErrorKind {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let kind = match self {
+            ReaderErrorKind::IncorrectHostname(_) => "IncorrectHostname",
+            ReaderErrorKind::CouldNotConnect(_) => "CouldNotConnect",
+            ReaderErrorKind::UnknownBackend(_) => "UnknownBackend",
+            ReaderErrorKind::LostConnection => "LostConnection",
+            ReaderErrorKind::NotConnected => "NotConnected",
+            ReaderErrorKind::Unsupported => "Unsupported",
+            ReaderErrorKind::InvalidParameter => "InvalidParameter",
+            ReaderErrorKind::InvalidArgument => "InvalidArgument",
+            ReaderErrorKind::DeviceError => "DeviceError",
+            ReaderErrorKind::Unknown => "Unknown",
+        };
+        write!(f, "{}", kind)
     }
 }
@@ -0,0 +1,343 @@
+use std::{
+    fs::File,
+    io::{self, Read},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex, Once,
+    },
+    thread,
+};
+
+use crate::tags::Tag;
+
+use super::{
+    info::ReaderInfo,
+    merge::ReaderConnection,
+    settings::{ReaderSettings, DEFAULT_ANTENNAS},
+    sink::ReaderSink,
+    ReaderError, ReaderErrorKind, ReaderProtocol, ReaderState,
+};
+
+/// Use this as hostname to read EPC lines from the standard input
+pub const STDIN_DEVICE: &str = "stdin";
+const DEFAULT_ANTENNA: u16 = 1;
+/// HID readers don't report a signal strength, so we treat every read as the strongest possible read
+const HID_STRENGTH: i8 = 0;
+
+/// `struct input_event` from `linux/input.h`: a `timeval` followed by a u16 type, u16 code and i32 value
+const INPUT_EVENT_SIZE: usize = 2 * std::mem::size_of::<usize>() + 8;
+const EV_KEY: u16 = 0x01;
+const KEY_PRESSED: i32 = 1;
+
+/// Where the lines of the standard input go, which is the stdin reader that connected last
+static STDIN_SUBSCRIBER: Mutex<Option<mpsc::Sender<io::Result<String>>>> = Mutex::new(None);
+static STDIN_THREAD: Once = Once::new();
+static STDIN_CLOSED: AtomicBool = AtomicBool::new(false);
+
+/// Interact with a keyboard-wedge (HID) reader, which types the EPC of every tag followed by Enter
+///
+/// The hostname of the connection is either [`STDIN_DEVICE`] or the path of an evdev input device
+/// (e.g. `/dev/input/event3`). Every reader reports its reads on the `antenna` option (default `1`),
+/// so a reader per circle can be assigned to a slot through the `slots` of its connection.
+#[derive(Debug)]
+pub struct HidReader {
+    path: String,
+    antenna: u16,
+    sink: ReaderSink,
+    /// Whether we pass reads on. The input itself is read for as long as this reader exists.
+    active: Arc<AtomicBool>,
+    closed: Arc<AtomicBool>,
+}
+
+impl HidReader {
+    pub fn new(connection: &ReaderConnection, sink: ReaderSink) -> Result<Self, ReaderError> {
+        let path = connection.hostname.clone();
+        let antenna = match connection.options.get("antenna") {
+            None => DEFAULT_ANTENNA,
+            Some(value) => value
+                .parse()
+                .ok()
+                .filter(|antenna| DEFAULT_ANTENNAS.contains(antenna))
+                .ok_or_else(|| ReaderError {
+                    kind: ReaderErrorKind::CouldNotConnect(path.clone()),
+                    message: format!("Option antenna has an invalid value {}", value),
+                })?,
+        };
+
+        sink.state(ReaderState::Connecting);
+        if path == STDIN_DEVICE {
+            Ok(HidReader::from_lines(
+                path,
+                subscribe_to_stdin(),
+                antenna,
+                sink,
+            ))
+        } else {
+            let device = File::open(&path).map_err(|err| ReaderError {
+                kind: ReaderErrorKind::CouldNotConnect(path.clone()),
+                message: err.to_string(),
+            })?;
+            Ok(HidReader::from_lines(
+                path,
+                EvdevLines::new(device),
+                antenna,
+                sink,
+            ))
+        }
+    }
+
+    /// Use any source of EPC lines
+    pub fn from_lines(
+        path: String,
+        lines: impl Iterator<Item = io::Result<String>> + Send + 'static,
+        antenna: u16,
+        sink: ReaderSink,
+    ) -> HidReader {
+        let active = Arc::new(AtomicBool::new(false));
+        let closed = Arc::new(AtomicBool::new(false));
+
+        // Reading input blocks until the next line arrives, so we can't stop this thread on command.
+        // Instead it keeps running in the background and we only pass on reads while we are active.
+        // Once closed, the line it is waiting for is dropped, which is why stdin is shared (see `subscribe_to_stdin`).
+        let thread_sink = sink.clone();
+        let thread_active = active.clone();
+        let thread_closed = closed.clone();
+        thread::spawn(move || {
+            for line in lines {
+                if thread_closed.load(Ordering::Relaxed) {
+                    return;
+                }
+                match line {
+                    Ok(line) if thread_active.load(Ordering::Relaxed) => {
+                        if let Some(tag) = parse_line(&line, antenna) {
+                            thread_sink.tags(vec![tag]);
+                        }
+                    }
+                    Ok(_) => (),
+                    Err(err) => {
+                        thread_sink.state(ReaderState::Failed);
+                        thread_sink.error(ReaderError {
+                            kind: ReaderErrorKind::LostConnection,
+                            message: err.to_string(),
+                        });
+                        return;
+                    }
+                }
+            }
+            if !thread_closed.load(Ordering::Relaxed) {
+                thread_sink.state(ReaderState::Disconnected);
+            }
+        });
+
+        sink.state(ReaderState::Ready);
+        HidReader {
+            path,
+            antenna,
+            sink,
+            active,
+            closed,
+        }
+    }
+}
+
+impl ReaderProtocol for HidReader {
+    fn start_reading(&mut self) -> Result<(), ReaderError> {
+        self.active.store(true, Ordering::Relaxed);
+        self.sink.state(ReaderState::Reading);
+        Ok(())
+    }
+
+    fn configure(&mut self, _settings: ReaderSettings) -> Result<(), ReaderError> {
+        // HID readers can't be configured by us
+        Ok(())
+    }
+
+    fn info(&self) -> Option<ReaderInfo> {
+        Some(ReaderInfo {
+            reader_id: Some(self.path.clone()),
+            firmware_version: String::from("hid"),
+            antenna_count: 1,
+            connected_antennas: vec![self.antenna],
+            ..Default::default()
+        })
+    }
+
+    fn stop_reading(&mut self, _await_confirmation: bool) -> Result<(), ReaderError> {
+        self.active.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+impl Drop for HidReader {
+    fn drop(&mut self) {
+        self.active.store(false, Ordering::Relaxed);
+        self.closed.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Receive the lines of the standard input, until another reader subscribes to them
+///
+/// A single thread reads the standard input for all readers. If every reader had its own thread, the thread of a
+/// closed reader would keep waiting for input after a reconnect and swallow the next line.
+fn subscribe_to_stdin() -> mpsc::IntoIter<io::Result<String>> {
+    let (sender, receiver) = mpsc::channel();
+    {
+        let mut subscriber = STDIN_SUBSCRIBER.lock().unwrap();
+        // Without input we drop the sender, so the reader sees the end of its input right away
+        if !STDIN_CLOSED.load(Ordering::Relaxed) {
+            // This disconnects the previous subscriber, which ends its thread
+            *subscriber = Some(sender);
+        }
+    }
+
+    STDIN_THREAD.call_once(|| {
+        thread::spawn(|| {
+            for line in io::stdin().lines() {
+                if let Some(subscriber) = STDIN_SUBSCRIBER.lock().unwrap().as_ref() {
+                    let _ = subscriber.send(line);
+                }
+            }
+            let mut subscriber = STDIN_SUBSCRIBER.lock().unwrap();
+            STDIN_CLOSED.store(true, Ordering::Relaxed);
+            subscriber.take();
+        });
+    });
+    receiver.into_iter()
+}
+
+fn parse_line(line: &str, antenna: u16) -> Option<Tag> {
+    let epc = line.trim().to_uppercase();
+    if epc.is_empty() || !epc.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Tag::build(epc, antenna, HID_STRENGTH).ok()
+}
+
+/// Turns the key presses of an evdev input device into lines
+///
+/// We only understand the keys a reader uses to type an EPC: hexadecimal digits and Enter.
+pub struct EvdevLines<R: Read> {
+    device: R,
+    line: String,
+}
+
+impl<R: Read> EvdevLines<R> {
+    pub fn new(device: R) -> EvdevLines<R> {
+        EvdevLines {
+            device,
+            line: String::new(),
+        }
+    }
+}
+
+impl<R: Read> Iterator for EvdevLines<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut event = [0u8; INPUT_EVENT_SIZE];
+        loop {
+            match self.device.read_exact(&mut event) {
+                Ok(()) => (),
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return None,
+                Err(err) => return Some(Err(err)),
+            }
+
+            let offset = INPUT_EVENT_SIZE - 8;
+            let event_type = u16::from_ne_bytes([event[offset], event[offset + 1]]);
+            let code = u16::from_ne_bytes([event[offset + 2], event[offset + 3]]);
+            let value = i32::from_ne_bytes(event[offset + 4..].try_into().unwrap());
+            if event_type != EV_KEY || value != KEY_PRESSED {
+                continue;
+            }
+
+            match key_to_char(code) {
+                Some('\n') => return Some(Ok(std::mem::take(&mut self.line))),
+                Some(c) => self.line.push(c),
+                None => (),
+            }
+        }
+    }
+}
+
+/// Map the keycodes of `linux/input-event-codes.h` to characters
+fn key_to_char(code: u16) -> Option<char> {
+    match code {
+        2..=10 => char::from_digit((code - 1).into(), 10),
+        11 => Some('0'),
+        30 => Some('A'),
+        48 => Some('B'),
+        46 => Some('C'),
+        32 => Some('D'),
+        18 => Some('E'),
+        33 => Some('F'),
+        28 | 96 => Some('\n'),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::sink::ReaderEvent;
+    use std::io::Cursor;
+
+    fn key_event(code: u16, value: i32) -> Vec<u8> {
+        let mut event = vec![0u8; INPUT_EVENT_SIZE - 8];
+        event.extend(EV_KEY.to_ne_bytes());
+        event.extend(code.to_ne_bytes());
+        event.extend(value.to_ne_bytes());
+        event
+    }
+
+    #[test]
+    fn should_decode_key_presses_into_lines() {
+        // E2 0A, followed by Enter. Releases should be ignored.
+        let events: Vec<u8> = [18, 3, 11, 30, 28]
+            .into_iter()
+            .flat_map(|code| [key_event(code, 1), key_event(code, 0)].concat())
+            .collect();
+
+        let lines: Vec<String> = EvdevLines::new(Cursor::new(events))
+            .map(|line| line.unwrap())
+            .collect();
+
+        assert_eq!(lines, vec![String::from("E20A")]);
+    }
+
+    #[test]
+    fn should_reject_antennas_without_a_circle() {
+        let (sink, _rx) = ReaderSink::channel(String::from(STDIN_DEVICE));
+        let mut connection = ReaderConnection::new(String::from(STDIN_DEVICE));
+        connection
+            .options
+            .insert(String::from("antenna"), String::from("4"));
+
+        let result = HidReader::new(&connection, sink);
+
+        assert!(result.is_err_and(|err| err.message.contains("antenna")));
+    }
+
+    #[test]
+    fn should_send_epc_lines_as_tags() {
+        let (sink, rx) = ReaderSink::channel(String::from(STDIN_DEVICE));
+        let (tx, lines) = std::sync::mpsc::channel::<io::Result<String>>();
+        let mut reader =
+            HidReader::from_lines(String::from(STDIN_DEVICE), lines.into_iter(), 2, sink);
+
+        reader.start_reading().unwrap();
+        tx.send(Ok(String::from("not an epc"))).unwrap();
+        tx.send(Ok(String::from("e2004702e3616828021e62e6\r")))
+            .unwrap();
+
+        let tag = rx
+            .iter()
+            .find_map(|event| match event {
+                ReaderEvent::Tags(tags) => tags.into_iter().next(),
+                _ => None,
+            })
+            .unwrap();
+
+        assert_eq!(tag.id, "E2004702E3616828021E62E6");
+        assert_eq!(tag.antenna, 2);
+    }
+}
@@ -0,0 +1,182 @@
+use llrp::{messages, parameters};
+
+/// The version of the LLRP standard we use to talk with our readers.
+///
+/// Version 1.0.1 has no way to negotiate a version, so this is always the version we speak.
+pub const LLRP_VERSION: &str = "1.0.1";
+
+/// Identity and capabilities of the reader we are connected to
+///
+/// This allows us to see which model and firmware we are using at an event.
+#[derive(Debug, Default, Clone, serde::Serialize)]
+pub struct ReaderInfo {
+    pub reader_id: Option<String>,
+    pub manufacturer: u32,
+    pub model: u32,
+    pub firmware_version: String,
+    pub llrp_version: String,
+    pub antenna_count: u16,
+    pub connected_antennas: Vec<u16>,
+    pub transmit_power_table: Vec<TransmitPowerLevel>,
+    pub features: Vec<String>,
+}
+
+/// One entry of the transmit power table of a reader
+///
+/// The index is what we use in a ROSpec, the value is the actual output power in dBm.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct TransmitPowerLevel {
+    pub index: u16,
+    pub dbm: f32,
+}
+
+impl ReaderInfo {
+    pub fn from_llrp(
+        capabilities: messages::GetReaderCapabilitiesResponse,
+        config: messages::GetReaderConfigResponse,
+    ) -> ReaderInfo {
+        let mut info = ReaderInfo {
+            llrp_version: LLRP_VERSION.to_string(),
+            ..Default::default()
+        };
+
+        if let Some(general) = capabilities.general_device_capabilities {
+            info.manufacturer = general.device_manufacturer_name;
+            info.model = general.model_name;
+            info.firmware_version = general.reader_firmware_version;
+            info.antenna_count = general.max_number_of_antenna_supported;
+            if general.can_set_antenna_properties {
+                info.features.push(String::from("SetAntennaProperties"));
+            }
+            if general.has_utc_clock_capability {
+                info.features.push(String::from("UTCClock"));
+            }
+        }
+
+        if let Some(llrp_capabilities) = capabilities.llrp_capabilities {
+            let features = [
+                (llrp_capabilities.can_do_rf_survey, "RFSurvey"),
+                (
+                    llrp_capabilities.can_report_buffer_fill_warning,
+                    "ReportBufferFillWarning",
+                ),
+                (
+                    llrp_capabilities.supports_client_request_op_spec,
+                    "ClientRequestOpSpec",
+                ),
+                (
+                    llrp_capabilities.can_do_tag_inventory_state_aware_singulation,
+                    "TagInventoryStateAwareSingulation",
+                ),
+                (
+                    llrp_capabilities.supports_event_and_report_holding,
+                    "EventAndReportHolding",
+                ),
+            ];
+            for (supported, name) in features {
+                if supported {
+                    info.features.push(name.to_string());
+                }
+            }
+        }
+
+        if let Some(uhf) = capabilities
+            .regulatory_capabilities
+            .and_then(|regulatory| regulatory.uhf_band_capabilities)
+        {
+            info.transmit_power_table = transmit_power_table(&uhf.transmit_power_level_table_entry);
+        }
+
+        info.reader_id = config.identification.map(|identification| {
+            identification
+                .reader_id
+                .iter()
+                .map(|byte| format!("{:02X?}", byte))
+                .collect()
+        });
+        info.connected_antennas = config
+            .antenna_properties
+            .iter()
+            .filter(|antenna| antenna.antenna_connected)
+            .map(|antenna| antenna.antenna_id)
+            .collect();
+
+        info
+    }
+
+    /// The highest index we can use as transmit power in a ROSpec
+    pub fn max_power_index(&self) -> Option<u16> {
+        self.transmit_power_table
+            .iter()
+            .map(|level| level.index)
+            .max()
+    }
+}
+
+fn transmit_power_table(
+    entries: &[parameters::TransmitPowerLevelTableEntry],
+) -> Vec<TransmitPowerLevel> {
+    entries
+        .iter()
+        .map(|entry| TransmitPowerLevel {
+            index: entry.index,
+            // LLRP expresses the power in hundredths of a dBm
+            dbm: entry.transmit_power_value as f32 / 100.0,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_convert_transmit_power_to_dbm() {
+        let entries = vec![
+            parameters::TransmitPowerLevelTableEntry {
+                index: 1,
+                transmit_power_value: 1000,
+            },
+            parameters::TransmitPowerLevelTableEntry {
+                index: 192,
+                transmit_power_value: 2920,
+            },
+        ];
+
+        let table = transmit_power_table(&entries);
+
+        assert_eq!(
+            table,
+            vec![
+                TransmitPowerLevel {
+                    index: 1,
+                    dbm: 10.0
+                },
+                TransmitPowerLevel {
+                    index: 192,
+                    dbm: 29.2
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn should_find_max_power_index() {
+        let info = ReaderInfo {
+            transmit_power_table: vec![
+                TransmitPowerLevel {
+                    index: 1,
+                    dbm: 10.0,
+                },
+                TransmitPowerLevel {
+                    index: 2,
+                    dbm: 10.1,
+                },
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(info.max_power_index(), Some(2));
+        assert_eq!(ReaderInfo::default().max_power_index(), None);
+    }
+}
@@ -0,0 +1,732 @@
+use std::{
+    collections::VecDeque,
+    io, net,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        mpsc::{self as std_mpsc, RecvTimeoutError},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use bytes::BytesMut;
+use futures::{SinkExt, StreamExt};
+use llrp::{
+    enumerations::AntennaEventType,
+    messages::{
+        GetReport, Keepalive, KeepaliveAck, Message, ReaderEventNotification, RoAccessReport,
+    },
+    BinaryMessage, LLRPMessage,
+};
+use rustls::{pki_types::ServerName, ClientConfig};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+    sync::mpsc,
+    time::{interval, timeout, MissedTickBehavior},
+};
+use tokio_rustls::TlsConnector;
+use tokio_util::codec::{Decoder, Encoder, Framed};
+
+use super::{
+    messages::handle_new_message, sink::ReaderSink, ReaderError, ReaderErrorKind, ReaderState,
+    RECV_TIMEOUT, REFRESH_INTERVAL,
+};
+
+/// Every LLRP message starts with the message type (2 bytes), its length (4 bytes) and its id (4 bytes)
+const HEADER_LENGTH: usize = 10;
+/// How long we wait for the reader to respond to a request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// If we don't hear from the reader for this long while reading, we assume the connection has failed
+const ALIVE_INTERVAL: Duration = Duration::from_millis((REFRESH_INTERVAL * 10) as u64);
+/// How many notifications we keep that arrived before anyone was waiting for them
+const MAX_UNCLAIMED: usize = 16;
+
+/// Splits a stream of bytes into LLRP messages, using the length in their header
+#[derive(Debug, Default)]
+pub struct LlrpCodec;
+
+impl Decoder for LlrpCodec {
+    type Item = BinaryMessage;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<BinaryMessage>, io::Error> {
+        if src.len() < HEADER_LENGTH {
+            return Ok(None);
+        }
+
+        let length = u32::from_be_bytes([src[2], src[3], src[4], src[5]]) as usize;
+        if length < HEADER_LENGTH {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Message length {} is shorter than its header", length),
+            ));
+        }
+        if src.len() < length {
+            src.reserve(length - src.len());
+            return Ok(None);
+        }
+
+        let frame = src.split_to(length);
+        llrp::read_message(&frame[..])
+            .map(Some)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))
+    }
+}
+
+impl Encoder<BinaryMessage> for LlrpCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, message: BinaryMessage, dst: &mut BytesMut) -> Result<(), io::Error> {
+        let mut buffer = Vec::new();
+        llrp::write_message(&mut buffer, message)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+        dst.extend_from_slice(&buffer);
+        Ok(())
+    }
+}
+
+type Reply = std_mpsc::Sender<Result<BinaryMessage, ReaderError>>;
+
+enum Command {
+    Send(BinaryMessage),
+    /// Send a message and reply with the response of the given type that carries the same message id
+    Request {
+        message: BinaryMessage,
+        response_type: u16,
+        reply: Reply,
+    },
+    /// Reply with the next notification of the given type
+    Await {
+        message_type: u16,
+        reply: Reply,
+    },
+    /// Pass reports on to the sink, and watch whether the reader is still alive
+    SetReporting(bool),
+    /// Ask the reader for its buffered reports every refresh interval while reporting
+    SetPolling(bool),
+    Shutdown,
+}
+
+/// A connection to an LLRP reader
+///
+/// All I/O happens on a small tokio runtime in its own thread, which answers keepalives and passes reports on to
+/// the sink. Responses are routed by their message type and id to the request they answer, so reports arriving
+/// in between (or responses to messages we sent without waiting) never get in the way of a request.
+///
+/// Our reader API is synchronous, so the methods of this struct block until the reader responds.
+/// Since the runtime is owned by this connection, this is also safe to use from within an async Tauri command.
+#[derive(Debug)]
+pub struct LlrpConnection {
+    commands: mpsc::UnboundedSender<Command>,
+    next_id: AtomicU32,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl LlrpConnection {
+    /// Start talking to the reader on the given stream, wrapped in TLS if a client config is given
+    ///
+    /// This returns once the TLS handshake is done, so a reader we don't trust never gets a connection.
+    pub fn spawn(
+        stream: net::TcpStream,
+        tls: Option<(Arc<ClientConfig>, ServerName<'static>)>,
+        sink: ReaderSink,
+    ) -> Result<LlrpConnection, ReaderError> {
+        let address = stream
+            .peer_addr()
+            .map(|address| address.to_string())
+            .unwrap_or_default();
+        let could_not_connect = |message: String| ReaderError {
+            kind: ReaderErrorKind::CouldNotConnect(address.clone()),
+            message,
+        };
+        stream
+            .set_nonblocking(true)
+            .map_err(|err| could_not_connect(err.to_string()))?;
+
+        let (commands, receiver) = mpsc::unbounded_channel();
+        let (ready, handshake) = std_mpsc::channel::<Result<(), String>>();
+        let thread = thread::spawn(move || {
+            let runtime = match tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+            {
+                Ok(runtime) => runtime,
+                Err(err) => {
+                    let _ = ready.send(Err(format!("Could not start runtime: {}", err)));
+                    return;
+                }
+            };
+            runtime.block_on(async move {
+                let stream = match TcpStream::from_std(stream) {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        let _ = ready.send(Err(err.to_string()));
+                        return;
+                    }
+                };
+                match tls {
+                    None => {
+                        let _ = ready.send(Ok(()));
+                        run(stream, receiver, sink).await
+                    }
+                    Some((config, server_name)) => {
+                        let connector = TlsConnector::from(config);
+                        match timeout(REQUEST_TIMEOUT, connector.connect(server_name, stream)).await
+                        {
+                            Ok(Ok(stream)) => {
+                                let _ = ready.send(Ok(()));
+                                run(stream, receiver, sink).await
+                            }
+                            Ok(Err(err)) => {
+                                let _ = ready.send(Err(format!("TLS handshake failed: {}", err)));
+                            }
+                            Err(_) => {
+                                let _ = ready.send(Err(format!(
+                                    "The reader did not finish the TLS handshake within {:?}",
+                                    REQUEST_TIMEOUT
+                                )));
+                            }
+                        }
+                    }
+                }
+            });
+        });
+
+        let result = handshake
+            .recv()
+            .unwrap_or_else(|_| Err(String::from("The connection stopped unexpectedly")));
+        if let Err(message) = result {
+            let _ = thread.join();
+            return Err(could_not_connect(message));
+        }
+
+        Ok(LlrpConnection {
+            commands,
+            next_id: AtomicU32::new(1),
+            thread: Some(thread),
+        })
+    }
+
+    /// Send a message without waiting for its response, which is dropped when it arrives
+    pub fn send(&self, message: Message) -> Result<(), ReaderError> {
+        self.command(Command::Send(self.encode(&message)))
+    }
+
+    /// Wait for the next notification of type `T`, which may have arrived already
+    ///
+    /// This is only meant for messages the reader sends on its own. Use `request` to get the response to a message.
+    pub fn await_message<T: LLRPMessage>(&self) -> Result<T, ReaderError> {
+        let (reply, response) = std_mpsc::channel();
+        self.command(Command::Await {
+            message_type: T::ID,
+            reply,
+        })?;
+        receive(response)
+    }
+
+    /// Send a message and wait for its response
+    pub fn request<T: LLRPMessage>(&self, message: Message) -> Result<T, ReaderError> {
+        let (reply, response) = std_mpsc::channel();
+        self.command(Command::Request {
+            message: self.encode(&message),
+            response_type: T::ID,
+            reply,
+        })?;
+        receive(response)
+    }
+
+    pub fn set_reporting(&self, reporting: bool) -> Result<(), ReaderError> {
+        self.command(Command::SetReporting(reporting))
+    }
+
+    pub fn set_polling(&self, polling: bool) -> Result<(), ReaderError> {
+        self.command(Command::SetPolling(polling))
+    }
+
+    /// Stop the connection, after sending the messages that are still queued
+    pub fn shutdown(&mut self) {
+        let _ = self.commands.send(Command::Shutdown);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+
+    fn encode(&self, message: &Message) -> BinaryMessage {
+        // NOTE: The message id can be any u32 - it is returned in the matching response
+        // We can unwrap here, since `from_dynamic_message` doesn't ever fail
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        BinaryMessage::from_dynamic_message(id, message).unwrap()
+    }
+
+    fn command(&self, command: Command) -> Result<(), ReaderError> {
+        self.commands.send(command).map_err(|_| closed())
+    }
+}
+
+/// Wait for the reply of the connection and decode it
+fn receive<T: LLRPMessage>(
+    response: std_mpsc::Receiver<Result<BinaryMessage, ReaderError>>,
+) -> Result<T, ReaderError> {
+    let message = match response.recv_timeout(REQUEST_TIMEOUT) {
+        Ok(result) => result?,
+        Err(RecvTimeoutError::Timeout) => {
+            return Err(ReaderError {
+                kind: ReaderErrorKind::LostConnection,
+                message: format!("The reader did not respond within {:?}", REQUEST_TIMEOUT),
+            })
+        }
+        Err(RecvTimeoutError::Disconnected) => return Err(closed()),
+    };
+
+    message.to_message::<T>().map_err(|err| ReaderError {
+        kind: ReaderErrorKind::Unknown,
+        message: format!("Could not decode message. Original error: {}", err),
+    })
+}
+
+impl Drop for LlrpConnection {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn closed() -> ReaderError {
+    ReaderError {
+        kind: ReaderErrorKind::LostConnection,
+        message: String::from("The connection with the reader is closed"),
+    }
+}
+
+async fn run<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: S,
+    mut commands: mpsc::UnboundedReceiver<Command>,
+    sink: ReaderSink,
+) {
+    let mut framed = Framed::new(stream, LlrpCodec);
+    let mut router = Router::new(sink);
+    let mut alive_check = interval(RECV_TIMEOUT);
+    let mut poll = interval(Duration::from_millis(REFRESH_INTERVAL.into()));
+    // After a pause in reading we only want a single request, not one for every interval we missed
+    poll.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        tokio::select! {
+            frame = framed.next() => match frame {
+                Some(Ok(message)) => {
+                    if let Some(response) = router.handle_message(message) {
+                        if let Err(err) = framed.send(response).await {
+                            router.lost_connection(err.to_string());
+                            break;
+                        }
+                    }
+                }
+                Some(Err(err)) => {
+                    router.lost_connection(err.to_string());
+                    break;
+                }
+                None => {
+                    router.lost_connection(String::from("The reader closed the connection"));
+                    break;
+                }
+            },
+            command = commands.recv() => match command {
+                Some(Command::Send(message)) => {
+                    if let Err(err) = framed.send(message).await {
+                        router.lost_connection(err.to_string());
+                        break;
+                    }
+                }
+                Some(Command::Request { message, response_type, reply }) => {
+                    // We wait for the response before sending, so it can't arrive before anyone is waiting for it
+                    router.await_response(response_type, message.message_id, reply);
+                    if let Err(err) = framed.send(message).await {
+                        router.lost_connection(err.to_string());
+                        break;
+                    }
+                }
+                Some(Command::Await { message_type, reply }) => router.await_message(message_type, reply),
+                Some(Command::SetReporting(reporting)) => router.set_reporting(reporting),
+                Some(Command::SetPolling(polling)) => router.polling = polling,
+                // If our handle is gone, nobody can use this connection anymore
+                Some(Command::Shutdown) | None => break,
+            },
+            _ = poll.tick(), if router.reporting && router.polling => {
+                let request =
+                    BinaryMessage::from_dynamic_message(0, &Message::GetReport(GetReport {})).unwrap();
+                if let Err(err) = framed.send(request).await {
+                    router.lost_connection(err.to_string());
+                    break;
+                }
+            },
+            _ = alive_check.tick() => {
+                if router.reporting && router.last_alive.elapsed() > ALIVE_INTERVAL {
+                    router.lost_connection(String::new());
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Decides where every incoming message should go
+struct Router {
+    sink: ReaderSink,
+    reporting: bool,
+    polling: bool,
+    last_alive: Instant,
+    /// Who is waiting for which message type, and for responses also for which message id
+    waiting: VecDeque<(u16, Option<u32>, Reply)>,
+    /// Notifications that arrived before anyone was waiting for them
+    unclaimed: VecDeque<BinaryMessage>,
+}
+
+impl Router {
+    fn new(sink: ReaderSink) -> Router {
+        Router {
+            sink,
+            reporting: false,
+            polling: false,
+            last_alive: Instant::now(),
+            waiting: VecDeque::new(),
+            unclaimed: VecDeque::new(),
+        }
+    }
+
+    fn set_reporting(&mut self, reporting: bool) {
+        self.reporting = reporting;
+        self.last_alive = Instant::now();
+    }
+
+    /// Handle a new message, returning the response we should send (if any)
+    fn handle_message(&mut self, mut message: BinaryMessage) -> Option<BinaryMessage> {
+        self.last_alive = Instant::now();
+
+        if message.message_type == Keepalive::ID {
+            if self.reporting {
+                // This lets the sink know the reader is still alive, even if there are no tokens
+                self.sink.tags(Vec::new());
+            }
+            return Some(
+                BinaryMessage::from_dynamic_message(0, &Message::KeepaliveAck(KeepaliveAck {}))
+                    .unwrap(),
+            );
+        }
+
+        if message.message_type == RoAccessReport::ID {
+            // Reports that arrive after we stopped reading are simply dropped
+            if self.reporting {
+                if let Ok(report) = message.to_dynamic_message() {
+                    self.sink.tags(handle_new_message(report, io::sink()));
+                }
+            }
+            return None;
+        }
+
+        if message.message_type == ReaderEventNotification::ID {
+            // Antenna events can arrive at any time, so nobody is waiting for them
+            if let Ok(Message::ReaderEventNotification(notification)) = message.to_dynamic_message()
+            {
+                if let Some(event) = notification.reader_event_notification_data.antenna_event {
+                    self.sink.antenna(
+                        event.antenna_id,
+                        event.event_type == AntennaEventType::Antenna_Connected,
+                    );
+                    return None;
+                }
+            }
+        }
+
+        while let Some(index) = self.waiting.iter().position(|(message_type, id, _)| {
+            *message_type == message.message_type && id.map_or(true, |id| id == message.message_id)
+        }) {
+            let (_, _, reply) = self.waiting.remove(index).unwrap();
+            match reply.send(Ok(message)) {
+                Ok(()) => return None,
+                // Nobody is waiting for this reply anymore (e.g. after a timeout), so we try the next one
+                Err(std_mpsc::SendError(Ok(returned))) => message = returned,
+                Err(_) => return None,
+            }
+        }
+
+        // Responses nobody is waiting for belong to messages we sent without waiting (or to a request that timed out),
+        // so only notifications are kept for later
+        if message.message_type == ReaderEventNotification::ID {
+            self.unclaimed.push_back(message);
+            if self.unclaimed.len() > MAX_UNCLAIMED {
+                self.unclaimed.pop_front();
+            }
+        }
+        None
+    }
+
+    fn await_response(&mut self, message_type: u16, message_id: u32, reply: Reply) {
+        self.waiting
+            .push_back((message_type, Some(message_id), reply));
+    }
+
+    fn await_message(&mut self, message_type: u16, reply: Reply) {
+        match self
+            .unclaimed
+            .iter()
+            .position(|message| message.message_type == message_type)
+        {
+            Some(index) => {
+                let message = self.unclaimed.remove(index).unwrap();
+                let _ = reply.send(Ok(message));
+            }
+            None => self.waiting.push_back((message_type, None, reply)),
+        }
+    }
+
+    fn lost_connection(&mut self, message: String) {
+        for (_, _, reply) in self.waiting.drain(..) {
+            let _ = reply.send(Err(ReaderError {
+                kind: ReaderErrorKind::LostConnection,
+                message: message.clone(),
+            }));
+        }
+
+        // While reading, nobody is waiting for a response, so we let the sink know
+        if self.reporting {
+            self.sink.state(ReaderState::Failed);
+            self.sink.error(ReaderError {
+                kind: ReaderErrorKind::LostConnection,
+                message,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        reader::{
+            messages::{construct_tag_report, write_message},
+            sink::ReaderEvent,
+        },
+        tags::{Tag, TagsMap},
+    };
+    use llrp::{
+        enumerations::StatusCode,
+        messages::{self, StopRospecResponse},
+        parameters::LLRPStatus,
+    };
+    use std::net::TcpListener;
+
+    fn connect() -> (
+        LlrpConnection,
+        net::TcpStream,
+        std_mpsc::Receiver<ReaderEvent>,
+    ) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (reader, _) = listener.accept().unwrap();
+        let (sink, events) = ReaderSink::channel(String::from("test"));
+        (
+            LlrpConnection::spawn(client, None, sink).unwrap(),
+            reader,
+            events,
+        )
+    }
+
+    fn encode(message: Message) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_message(&mut bytes, message, None).unwrap();
+        bytes
+    }
+
+    /// A report of reads, given as the last byte of their EPC, their antenna and RSSI
+    fn report(reads: &[(u8, u16, i8)]) -> Message {
+        let tag_report_data = reads
+            .iter()
+            .map(|&(epc, antenna, rssi)| {
+                let mut data = construct_tag_report(antenna, rssi);
+                let mut id = [0; 12];
+                id[11] = epc;
+                data.epc_parameter = llrp::choices::EPCParameter::EPC_96(id);
+                data
+            })
+            .collect();
+        Message::RoAccessReport(messages::RoAccessReport {
+            tag_report_data,
+            rf_survey_report_data: Vec::new(),
+            custom: Vec::new(),
+        })
+    }
+
+    /// Put the next `count` tags that are sent to the sink in a `TagsMap`
+    fn collect_tags(events: &std_mpsc::Receiver<ReaderEvent>, count: usize) -> TagsMap {
+        let mut tags: Vec<Tag> = Vec::new();
+        while tags.len() < count {
+            if let ReaderEvent::Tags(new_tags) = events.recv_timeout(REQUEST_TIMEOUT).unwrap() {
+                tags.extend(new_tags);
+            }
+        }
+        let mut tags_map = TagsMap::new();
+        tags_map.add_tags(tags);
+        tags_map
+    }
+
+    /// Let a simulated reader report a stream of reads, given per refresh interval, and collect the results
+    ///
+    /// In real-time mode the reader reports every read as soon as it happens. When polled, it buffers the reads of
+    /// an interval until we ask for them.
+    fn read_stream(polled: bool, stream: &[&[(u8, u16, i8)]]) -> TagsMap {
+        let (connection, reader, events) = connect();
+        connection.set_polling(polled).unwrap();
+        connection.set_reporting(true).unwrap();
+        thread::sleep(RECV_TIMEOUT);
+
+        for reads in stream {
+            if polled {
+                let request = llrp::read_message(&reader).unwrap();
+                assert_eq!(request.message_type, messages::GetReport::ID);
+                write_message(&reader, report(reads), None).unwrap();
+            } else {
+                for &read in reads.iter() {
+                    write_message(&reader, report(&[read]), None).unwrap();
+                }
+            }
+        }
+        collect_tags(&events, stream.iter().map(|reads| reads.len()).sum())
+    }
+
+    /// The antenna and peak RSSI of every token
+    fn results(tags_map: &TagsMap) -> Vec<(String, u16, i8)> {
+        let mut results: Vec<(String, u16, i8)> = tags_map
+            .values()
+            .map(|tag| (tag.id.clone(), tag.antenna, tag.peak_strength))
+            .collect();
+        results.sort();
+        results
+    }
+
+    fn stop_rospec_response() -> Message {
+        Message::StopRospecResponse(StopRospecResponse {
+            status: LLRPStatus {
+                status_code: StatusCode::M_Success,
+                error_description: String::from(""),
+                field_error: None,
+                parameter_error: None,
+            },
+        })
+    }
+
+    fn reader_event_notification() -> Message {
+        Message::ReaderEventNotification(messages::ReaderEventNotification {
+            reader_event_notification_data: llrp::parameters::ReaderEventNotificationData {
+                connection_attempt_event: Some(llrp::parameters::ConnectionAttemptEvent {
+                    status: llrp::enumerations::ConnectionAttemptStatusType::Success,
+                }),
+                ..Default::default()
+            },
+        })
+    }
+
+    #[test]
+    fn should_decode_messages_split_over_multiple_reads() {
+        let bytes = encode(Message::Keepalive(messages::Keepalive {}));
+        let mut codec = LlrpCodec;
+        let mut buffer = BytesMut::from(&bytes[..4]);
+
+        assert!(codec.decode(&mut buffer).unwrap().is_none());
+        buffer.extend_from_slice(&bytes[4..]);
+        let message = codec.decode(&mut buffer).unwrap().unwrap();
+
+        assert_eq!(message.message_type, messages::Keepalive::ID);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn should_answer_keepalive() {
+        let (_connection, reader, _events) = connect();
+
+        write_message(&reader, Message::Keepalive(messages::Keepalive {}), None).unwrap();
+        let response = llrp::read_message(&reader).unwrap();
+
+        assert_eq!(response.message_type, KeepaliveAck::ID);
+    }
+
+    #[test]
+    fn should_keep_notifications_that_arrive_early() {
+        let (connection, reader, _events) = connect();
+
+        write_message(&reader, Message::Keepalive(messages::Keepalive {}), None).unwrap();
+        write_message(&reader, reader_event_notification(), None).unwrap();
+
+        assert!(connection
+            .await_message::<messages::ReaderEventNotification>()
+            .is_ok());
+    }
+
+    #[test]
+    fn should_match_responses_by_message_id() {
+        let (connection, reader, _events) = connect();
+        let responder = thread::spawn(move || {
+            let unanswered = llrp::read_message(&reader).unwrap();
+            let request = llrp::read_message(&reader).unwrap();
+            // The late response to the first message arrives while we wait for the second one
+            let failed = Message::StopRospecResponse(StopRospecResponse {
+                status: LLRPStatus {
+                    status_code: StatusCode::R_DeviceError,
+                    error_description: String::from("Late response"),
+                    field_error: None,
+                    parameter_error: None,
+                },
+            });
+            write_message(&reader, failed, Some(unanswered.message_id)).unwrap();
+            write_message(&reader, stop_rospec_response(), Some(request.message_id)).unwrap();
+        });
+
+        let stop = || Message::StopRospec(messages::StopRospec { ro_spec_id: 1 });
+        connection.send(stop()).unwrap();
+        let response = connection.request::<StopRospecResponse>(stop()).unwrap();
+        responder.join().unwrap();
+
+        assert_eq!(response.status.status_code, StatusCode::M_Success);
+    }
+
+    #[test]
+    fn should_poll_reports_with_same_results_as_realtime() {
+        // The reads that happen during two refresh intervals, including a token that moves to another antenna
+        let stream: [&[(u8, u16, i8)]; 2] = [
+            &[(1, 1, -40), (2, 2, -50), (1, 1, -38)],
+            &[(3, 3, -60), (2, 2, -52), (1, 2, -45)],
+        ];
+
+        let realtime = read_stream(false, &stream);
+        let polled = read_stream(true, &stream);
+
+        assert_eq!(results(&realtime), results(&polled));
+        assert_eq!(
+            results(&polled),
+            [
+                (String::from("000000000000000000000001"), 2, -45),
+                (String::from("000000000000000000000002"), 2, -50),
+                (String::from("000000000000000000000003"), 3, -60),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_report_lost_connection_while_reading() {
+        let (connection, reader, events) = connect();
+
+        connection.set_reporting(true).unwrap();
+        // Make sure the connection handled our command before the reader disappears
+        thread::sleep(RECV_TIMEOUT);
+        drop(reader);
+
+        let error = events
+            .iter()
+            .find_map(|event| match event {
+                ReaderEvent::Error(error) => Some(error),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(error.kind, ReaderErrorKind::LostConnection);
+    }
+}
@@ -4,17 +4,20 @@ use llrp::{
     parameters, LLRPMessage,
 };
 use std::{
-    io::Write,
     net::{self, TcpStream},
     time::Duration,
 };
-use tauri::{async_runtime::JoinHandle, AppHandle, Manager};
 
 use super::{
-    handle_reader_input,
-    messages::{parse_message_and, write_message},
-    rospec::construct_default_rospec,
-    ReaderError, ReaderErrorKind, ReaderProtocol, DEFAULT_ROSPEC_ID, REFRESH_INTERVAL,
+    info::ReaderInfo,
+    llrp_connection::LlrpConnection,
+    messages::check_status,
+    rospec::construct_rospec,
+    settings::{ReaderSettings, ReportMode},
+    sink::ReaderSink,
+    tls::TlsOptions,
+    tls::TLS_PORT,
+    ReaderError, ReaderErrorKind, ReaderProtocol, ReaderState, DEFAULT_ROSPEC_ID, REFRESH_INTERVAL,
 };
 
 const DEFAULT_PORT: u16 = 5084;
@@ -27,17 +30,26 @@ const DEFAULT_PORT: u16 = 5084;
 /// cannot use feature from version 1.1.0
 ///
 /// We are also able to use the custom extensions made by Zebra. See [Zebra's docs for the available custom extensions](https://www.zebra.com/content/dam/support-dam/en/documentation/unrestricted/guide/software/interface-control-guide-en.pdf)
+///
+/// ## TLS
+/// If TLS options are given, we connect on port 5085 instead and wrap the connection in TLS.
 #[derive(Debug)]
 pub struct LLRPReader {
     hostname: String,
-    stream: Option<TcpStream>,
-    handle: Option<JoinHandle<()>>,
+    tls: Option<TlsOptions>,
+    connection: Option<LlrpConnection>,
+    sink: ReaderSink,
+    reading: bool,
+    info: Option<ReaderInfo>,
+    settings: ReaderSettings,
 }
 
-impl ReaderProtocol for LLRPReader {
-    fn new<R: tauri::Runtime>(
+impl LLRPReader {
+    pub fn new(
         hostname: String,
-        app_handle: AppHandle<R>,
+        tls: Option<TlsOptions>,
+        settings: ReaderSettings,
+        sink: ReaderSink,
     ) -> Result<Self, ReaderError> {
         if hostname.len() != 12 {
             return Err(ReaderError {
@@ -51,51 +63,71 @@ impl ReaderProtocol for LLRPReader {
 
         let mut reader = LLRPReader {
             hostname,
-            stream: None,
-            handle: None,
+            tls,
+            connection: None,
+            sink,
+            reading: false,
+            info: None,
+            settings,
         };
-        // NOTE: We log every step of the connection process to make it easier to understand when something goes wrong
-        // We always ignore the output these emit events, since we don't care if this fails.
-        let _ = app_handle.emit_all("connection-status", "Start connecting to reader");
-        reader.connect(app_handle.clone())?;
-        let _ = app_handle.emit_all("connection-status", "Start preparing reader for usage");
-        reader.prepare(app_handle.clone())?;
-        let _ = app_handle.emit_all("connection-status", "Connected and ready to go");
+        // NOTE: We report every step of the connection process to make it easier to understand when something goes wrong
+        reader.sink.state(ReaderState::Connecting);
+        reader.connect()?;
+        reader.sink.state(ReaderState::Configuring);
+        reader.query_info()?;
+        reader.prepare()?;
+        reader.sink.state(ReaderState::Ready);
         Ok(reader)
     }
+}
 
-    fn start_reading<R: tauri::Runtime>(
-        &mut self,
-        app_handle: AppHandle<R>,
-    ) -> Result<(), ReaderError> {
+impl ReaderProtocol for LLRPReader {
+    fn start_reading(&mut self) -> Result<(), ReaderError> {
         // Just in case we are already reading, we should try to stop
-        let _ = app_handle.emit_all("connection-status", "Stopping previous session");
         self.stop_reading(true)?;
-        let _ = app_handle.emit_all("connection-status", "Previous session stopped");
 
         // Actually start
-        self.write_message(Message::StartRospec(messages::StartRospec {
-            ro_spec_id: DEFAULT_ROSPEC_ID,
-        }))?;
-        let _ = app_handle.emit_all("connection-status", "Started new session");
-
-        let stream = self.stream.as_ref().unwrap().try_clone().unwrap();
-        let handle = handle_reader_input::<R>(stream, app_handle.clone());
-        self.handle = Some(handle);
-        let _ = app_handle.emit_all("connection-status", "Ready to receive messages");
+        let response = self.request::<messages::StartRospecResponse>(Message::StartRospec(
+            messages::StartRospec {
+                ro_spec_id: DEFAULT_ROSPEC_ID,
+            },
+        ))?;
+        check_status(&response.status)?;
+
+        let connection = self.connection()?;
+        connection.set_polling(self.settings.report_mode == ReportMode::Polled)?;
+        connection.set_reporting(true)?;
+        self.reading = true;
+        self.sink.state(ReaderState::Reading);
         Ok(())
     }
 
+    fn configure(&mut self, settings: ReaderSettings) -> Result<(), ReaderError> {
+        if self.reading {
+            self.stop_reading(true)?;
+        }
+        self.settings = settings;
+        self.upload_rospec()
+    }
+
+    fn info(&self) -> Option<ReaderInfo> {
+        self.info.clone()
+    }
+
     fn stop_reading(&mut self, await_confirmation: bool) -> Result<(), ReaderError> {
-        if let Some(handle) = self.handle.take() {
-            handle.abort();
-        };
+        self.reading = false;
+        let connection = self.connection()?;
+        connection.set_reporting(false)?;
 
-        self.write_message(Message::StopRospec(messages::StopRospec {
+        let message = Message::StopRospec(messages::StopRospec {
             ro_spec_id: DEFAULT_ROSPEC_ID,
-        }))?;
+        });
         if await_confirmation {
-            self.await_message::<messages::StopRospecResponse>()?;
+            // NOTE: We don't check the status here, since the reader returns an error when the ROSpec was not active.
+            // For us, that simply means we have nothing to stop.
+            connection.request::<messages::StopRospecResponse>(message)?;
+        } else {
+            connection.send(message)?;
         }
         Ok(())
     }
@@ -106,18 +138,18 @@ impl LLRPReader {
     ///
     /// We first try the hostname and check if we can connect that way.
     /// If the hostname is unavailable, we fall back on the LinkLocal ipv4.
-    fn connect<R: tauri::Runtime>(&mut self, app_handle: AppHandle<R>) -> Result<(), ReaderError> {
-        self.stream = match net::TcpStream::connect(format!("{}:{}", self.hostname, DEFAULT_PORT)) {
-            Ok(stream) => Some(stream),
-            Err(_) => None,
+    fn connect(&mut self) -> Result<(), ReaderError> {
+        let port = match self.tls {
+            Some(_) => TLS_PORT,
+            None => DEFAULT_PORT,
         };
-
-        if self.stream.is_none() {
-            self.stream = match net::TcpStream::connect_timeout(
-                &net::SocketAddr::new(self.hostname_as_ip()?, DEFAULT_PORT),
+        let stream = match net::TcpStream::connect(format!("{}:{}", self.hostname, port)) {
+            Ok(stream) => stream,
+            Err(_) => match net::TcpStream::connect_timeout(
+                &net::SocketAddr::new(self.hostname_as_ip()?, port),
                 Duration::from_secs(5),
             ) {
-                Ok(stream) => Some(stream),
+                Ok(stream) => stream,
                 Err(err) => {
                     #[cfg(debug_assertions)]
                     println!("Connection error {:#?}", err);
@@ -126,23 +158,45 @@ impl LLRPReader {
                         message: err.to_string(),
                     });
                 }
-            };
-        }
-        let _ = app_handle.emit_all("connection-status", "Opened connection to reader");
-
-        // Wait for the first ReaderEventNotification and confirm that we are connected
-        self.await_message_and::<messages::ReaderEventNotification>(
-            |m: &messages::ReaderEventNotification| {
-                m.reader_event_notification_data.connection_attempt_event
-                    == Some(parameters::ConnectionAttemptEvent {
-                        status: enumerations::ConnectionAttemptStatusType::Success,
-                    })
             },
-        )?;
-        let _ = app_handle.emit_all("connection-status", "Receiving connection ");
+        };
+        self.open(stream)
+    }
 
-        // Just in case the reader is holding on to some old reports, we try to flush the stream
-        let _ = self.stream.as_ref().unwrap().flush();
+    /// Start talking LLRP over a stream that is connected to the reader
+    fn open(&mut self, stream: TcpStream) -> Result<(), ReaderError> {
+        let tls = match &self.tls {
+            Some(options) => Some((
+                options.client_config()?,
+                options.server_name(&self.hostname)?,
+            )),
+            None => None,
+        };
+        self.connection = Some(LlrpConnection::spawn(stream, tls, self.sink.clone())?);
+        self.sink.state(ReaderState::Handshaking);
+
+        // Wait for the first ReaderEventNotification and confirm that we are connected
+        let notification = self
+            .connection()?
+            .await_message::<messages::ReaderEventNotification>()?;
+        let expected = Some(parameters::ConnectionAttemptEvent {
+            status: enumerations::ConnectionAttemptStatusType::Success,
+        });
+        if notification
+            .reader_event_notification_data
+            .connection_attempt_event
+            != expected
+        {
+            #[cfg(debug_assertions)]
+            println!(
+                "Reader did not accept our connection. Original message {:#?}",
+                notification
+            );
+            return Err(ReaderError {
+                kind: ReaderErrorKind::CouldNotConnect(self.hostname.clone()),
+                message: String::from("The reader did not accept our connection"),
+            });
+        }
         Ok(())
     }
 
@@ -172,103 +226,94 @@ impl LLRPReader {
         ]))
     }
 
-    fn write_message(&self, message: Message) -> Result<(), ReaderError> {
-        write_message(self.stream.as_ref().unwrap(), message, None)
+    fn connection(&self) -> Result<&LlrpConnection, ReaderError> {
+        self.connection.as_ref().ok_or(ReaderError {
+            kind: ReaderErrorKind::NotConnected,
+            message: String::new(),
+        })
     }
 
-    fn parse_message_and<T: LLRPMessage>(
-        &mut self,
-        closure: fn(message: &T) -> bool,
-    ) -> Result<T, ReaderError> {
-        let stream = self.stream.as_mut().unwrap();
-        parse_message_and(stream, closure)
+    fn request<T: LLRPMessage>(&self, message: Message) -> Result<T, ReaderError> {
+        self.connection()?.request(message)
     }
 
-    fn parse_message<T: LLRPMessage>(&mut self) -> Result<T, ReaderError> {
-        self.parse_message_and(|_| true)
-    }
-
-    fn await_message_and<T: LLRPMessage + std::fmt::Debug>(
-        &mut self,
-        closure: fn(message: &T) -> bool,
-    ) -> Result<T, ReaderError> {
-        let message = loop {
-            match self.parse_message::<T>() {
-                Ok(message) => break message,
-                Err(_) => (),
-            }
-        };
-
-        match closure(&message) {
-            true => Ok(message),
-            false => {
-                #[cfg(debug_assertions)]
-                println!(
-                    "Message did not pass closure check. Original message {:#?}",
-                    message
-                );
-                Err(ReaderError {
-                    kind: ReaderErrorKind::Unknown,
-                    message: String::from("Message did not pass closure check."),
-                })
-            }
-        }
-    }
+    /// Ask the reader who it is and what it can do
+    fn query_info(&mut self) -> Result<(), ReaderError> {
+        let capabilities = self.request::<messages::GetReaderCapabilitiesResponse>(
+            Message::GetReaderCapabilities(messages::GetReaderCapabilities {
+                requested_data: enumerations::GetReaderCapabilitiesRequestedData::All,
+            }),
+        )?;
+        check_status(&capabilities.status)?;
+
+        let config = self.request::<messages::GetReaderConfigResponse>(
+            Message::GetReaderConfig(messages::GetReaderConfig {
+                antenna_id: 0, // Antenna ID 0 means we want the config of all antennas
+                requested_data: enumerations::GetReaderConfigRequestedData::All,
+                gpi_port_num: 0,
+                gpo_port_num: 0,
+                custom: Vec::new(),
+            }),
+        )?;
+        check_status(&config.status)?;
 
-    fn await_message<T: LLRPMessage + std::fmt::Debug>(&mut self) -> Result<T, ReaderError> {
-        self.await_message_and(|_| true)
+        self.info = Some(ReaderInfo::from_llrp(capabilities, config));
+        Ok(())
     }
 
-    fn prepare<R: tauri::Runtime>(&mut self, app_handle: AppHandle<R>) -> Result<(), ReaderError> {
-        // Set reader config to emit keepalive messages
-        self.write_message(Message::SetReaderConfig(messages::SetReaderConfig {
-            reset_to_factory_default: true,
-            reserved: 0, // Unclear what this field is for
-            reader_event_notification_spec: None,
-            antenna_properties: Vec::new(),
-            antenna_configuration: Vec::new(),
-            ro_report_spec: None,
-            access_report_spec: None,
-            keepalive_spec: Some(parameters::KeepaliveSpec {
-                keepalive_trigger_type: enumerations::KeepaliveTriggerType::Periodic,
-                periodic_trigger_value: REFRESH_INTERVAL / 2,
+    fn prepare(&mut self) -> Result<(), ReaderError> {
+        // Set reader config to emit keepalive messages and let us know when an antenna is (dis)connected
+        let response = self.request::<messages::SetReaderConfigResponse>(
+            Message::SetReaderConfig(messages::SetReaderConfig {
+                reset_to_factory_default: true,
+                reserved: 0, // Unclear what this field is for
+                reader_event_notification_spec: Some(parameters::ReaderEventNotificationSpec {
+                    event_notification_state: vec![parameters::EventNotificationState {
+                        event_type: enumerations::NotificationEventType::Antenna_Event,
+                        notification_state: true,
+                    }],
+                }),
+                antenna_properties: Vec::new(),
+                antenna_configuration: Vec::new(),
+                ro_report_spec: None,
+                access_report_spec: None,
+                keepalive_spec: Some(parameters::KeepaliveSpec {
+                    keepalive_trigger_type: enumerations::KeepaliveTriggerType::Periodic,
+                    periodic_trigger_value: REFRESH_INTERVAL / 2,
+                }),
+                gpo_write_data: Vec::new(),
+                gpi_port_current_state: Vec::new(),
+                events_and_reports: None,
+                custom: Vec::new(),
             }),
-            gpo_write_data: Vec::new(),
-            gpi_port_current_state: Vec::new(),
-            events_and_reports: None,
-            custom: Vec::new(),
-        }))?;
-        self.await_message_and::<messages::SetReaderConfigResponse>(|m| {
-            m.status.status_code == enumerations::StatusCode::M_Success
-        })?;
-        let _ = app_handle.emit_all("connection-status", "Reset reader settings");
+        )?;
+        check_status(&response.status)?;
+
+        self.upload_rospec()
+    }
 
+    /// Replace the ro_spec in the reader by one that uses our current settings
+    fn upload_rospec(&mut self) -> Result<(), ReaderError> {
         // Remove all existing ro_specs in the reader. ro_spec_id `0` means all ro_spec's should be deleted
-        self.write_message(Message::DeleteRospec(messages::DeleteRospec {
-            ro_spec_id: 0,
-        }))?;
-        self.await_message_and::<messages::DeleteRospecResponse>(|m| {
-            m.status.status_code == enumerations::StatusCode::M_Success
-        })?;
-        let _ = app_handle.emit_all("connection-status", "Removed old reader config");
+        let response = self.request::<messages::DeleteRospecResponse>(Message::DeleteRospec(
+            messages::DeleteRospec { ro_spec_id: 0 },
+        ))?;
+        check_status(&response.status)?;
 
         // Add our new ro_spec
-        self.write_message(Message::AddRospec(messages::AddRospec {
-            ro_spec: construct_default_rospec(),
-        }))?;
-        self.await_message_and::<messages::AddRospecResponse>(|m| {
-            m.status.status_code == enumerations::StatusCode::M_Success
-        })?;
-        let _ = app_handle.emit_all("connection-status", "Added new reader config");
+        let response =
+            self.request::<messages::AddRospecResponse>(Message::AddRospec(messages::AddRospec {
+                ro_spec: construct_rospec(&self.settings),
+            }))?;
+        check_status(&response.status)?;
 
         // Enable our new ro_spec
-        self.write_message(Message::EnableRospec(messages::EnableRospec {
-            ro_spec_id: DEFAULT_ROSPEC_ID,
-        }))?;
-        self.await_message_and::<messages::EnableRospecResponse>(|m| {
-            m.status.status_code == enumerations::StatusCode::M_Success
-        })?;
-        let _ = app_handle.emit_all("connection-status", "Enabled reader config");
+        let response = self.request::<messages::EnableRospecResponse>(Message::EnableRospec(
+            messages::EnableRospec {
+                ro_spec_id: DEFAULT_ROSPEC_ID,
+            },
+        ))?;
+        check_status(&response.status)?;
 
         Ok(())
     }
@@ -277,11 +322,11 @@ impl LLRPReader {
 impl Drop for LLRPReader {
     fn drop(&mut self) {
         // When dropping, we don't actually care for the responses
-        if self.handle.is_some() {
+        if self.reading {
             let _ = self.stop_reading(false);
         }
-        if self.stream.is_some() {
-            let _ = self.write_message(Message::CloseConnection(messages::CloseConnection {}));
+        if let Some(connection) = &self.connection {
+            let _ = connection.send(Message::CloseConnection(messages::CloseConnection {}));
         }
     }
 }
@@ -295,8 +340,12 @@ mod tests {
     fn should_convert_hostname_to_ip() {
         let reader = LLRPReader {
             hostname: "fx9600749620".to_string(),
-            stream: None,
-            handle: None,
+            tls: None,
+            connection: None,
+            sink: ReaderSink::channel(String::from("test")).0,
+            reading: false,
+            info: None,
+            settings: ReaderSettings::default(),
         };
         let ipv4 = reader.hostname_as_ip();
 
@@ -308,8 +357,12 @@ mod tests {
     fn should_return_err_if_hostname_cannot_convert() {
         let reader = LLRPReader {
             hostname: "fx960074XX20".to_string(),
-            stream: None,
-            handle: None,
+            tls: None,
+            connection: None,
+            sink: ReaderSink::channel(String::from("test")).0,
+            reading: false,
+            info: None,
+            settings: ReaderSettings::default(),
         };
         let ipv4 = reader.hostname_as_ip();
 
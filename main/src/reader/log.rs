@@ -0,0 +1,29 @@
+use std::fmt::{Display, Formatter};
+
+/// What happened with a reader, as stored in the `reader_events` table
+///
+/// Together with the time and session of every entry, this lets us find out afterwards why
+/// a step had fewer answers than expected.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReaderLogKind {
+    /// The reader went to a new state, e.g. while connecting, handshaking, reconnecting or when it starts reading
+    State,
+    /// Something went wrong with the reader
+    Error,
+    /// An antenna was connected or disconnected
+    Antenna,
+    /// We stopped reading
+    Stopped,
+}
+
+impl Display for ReaderLogKind {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let kind = match self {
+            ReaderLogKind::State => "State",
+            ReaderLogKind::Error => "Error",
+            ReaderLogKind::Antenna => "Antenna",
+            ReaderLogKind::Stopped => "Stopped",
+        };
+        write!(f, "{}", kind)
+    }
+}
@@ -0,0 +1,181 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, Instant},
+};
+
+use crate::tags::Tag;
+
+use super::registry::DEFAULT_BACKEND;
+
+/// How long a reader keeps its claim on a token when another reader sees that token as well
+const CONFLICT_WINDOW: Duration = Duration::from_millis(1000);
+
+/// How to connect to a single reader, and which circle (option slot) every antenna of that reader covers
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ReaderConnection {
+    /// The address of the reader. This also identifies the reader within a session.
+    pub hostname: String,
+    /// The name of the backend in the `ReaderRegistry`
+    #[serde(default = "default_backend")]
+    pub backend: String,
+    /// Extra options that are specific to the backend
+    #[serde(default)]
+    pub options: BTreeMap<String, String>,
+    /// Maps the antennas of this reader to an option slot. If this is empty, every antenna is its own slot.
+    #[serde(default)]
+    pub slots: BTreeMap<u16, u16>,
+}
+
+impl ReaderConnection {
+    pub fn new(hostname: String) -> ReaderConnection {
+        ReaderConnection {
+            hostname,
+            backend: default_backend(),
+            options: BTreeMap::new(),
+            slots: BTreeMap::new(),
+        }
+    }
+}
+
+fn default_backend() -> String {
+    String::from(DEFAULT_BACKEND)
+}
+
+#[derive(Debug)]
+struct Claim {
+    reader: String,
+    strength: i8,
+    seen_at: Instant,
+}
+
+/// Merges the tags of multiple readers into a single set of option slots
+///
+/// When two readers see the same token at the same time (e.g. a token lying between two circles of different readers),
+/// the reader with the strongest read keeps the token until it hasn't seen it for a while.
+#[derive(Debug, Default)]
+pub struct TagMerger {
+    slots: HashMap<String, BTreeMap<u16, u16>>,
+    claims: HashMap<String, Claim>,
+}
+
+impl TagMerger {
+    pub fn new(connections: &[ReaderConnection]) -> TagMerger {
+        TagMerger {
+            slots: connections
+                .iter()
+                .map(|connection| (connection.hostname.clone(), connection.slots.clone()))
+                .collect(),
+            claims: HashMap::new(),
+        }
+    }
+
+    /// Forget which reader saw which token, e.g. when we start a new question
+    pub fn reset(&mut self) {
+        self.claims.clear();
+    }
+
+    /// Convert the antennas of the tags to option slots and drop the tags that lose a conflict
+    pub fn merge(&mut self, reader: &str, tags: Vec<Tag>) -> Vec<Tag> {
+        self.merge_at(reader, tags, Instant::now())
+    }
+
+    fn merge_at(&mut self, reader: &str, tags: Vec<Tag>, now: Instant) -> Vec<Tag> {
+        tags.into_iter()
+            .filter_map(|mut tag| {
+                tag.antenna = self.slot(reader, tag.antenna)?;
+
+                if let Some(claim) = self.claims.get(&tag.id) {
+                    let is_conflict = claim.reader != reader
+                        && now.saturating_duration_since(claim.seen_at) < CONFLICT_WINDOW;
+                    if is_conflict && claim.strength > tag.strength {
+                        return None;
+                    }
+                }
+
+                self.claims.insert(
+                    tag.id.clone(),
+                    Claim {
+                        reader: reader.to_string(),
+                        strength: tag.strength,
+                        seen_at: now,
+                    },
+                );
+                Some(tag)
+            })
+            .collect()
+    }
+
+    /// The option slot an antenna of a reader covers, if any
+    pub fn slot(&self, reader: &str, antenna: u16) -> Option<u16> {
+        match self.slots.get(reader) {
+            Some(slots) if !slots.is_empty() => slots.get(&antenna).copied(),
+            // Without a mapping, we use the antenna as slot
+            _ => Some(antenna),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(id: &str, antenna: u16, strength: i8) -> Tag {
+        Tag::new(String::from(id), antenna, strength)
+    }
+
+    fn merger() -> TagMerger {
+        TagMerger::new(&[
+            ReaderConnection {
+                slots: BTreeMap::from([(1, 1), (2, 2), (3, 3)]),
+                ..ReaderConnection::new(String::from("left"))
+            },
+            ReaderConnection {
+                slots: BTreeMap::from([(1, 4), (2, 5), (3, 6)]),
+                ..ReaderConnection::new(String::from("right"))
+            },
+        ])
+    }
+
+    #[test]
+    fn should_map_antennas_to_slots() {
+        let mut merger = merger();
+
+        let tags = merger.merge("right", vec![tag("abc", 2, -40)]);
+
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags[0].antenna, 5);
+    }
+
+    #[test]
+    fn should_drop_unmapped_antennas() {
+        let mut merger = merger();
+
+        let tags = merger.merge("left", vec![tag("abc", 4, -40)]);
+
+        assert!(tags.is_empty());
+    }
+
+    #[test]
+    fn should_keep_strongest_reader_during_conflict() {
+        let mut merger = merger();
+        let now = Instant::now();
+
+        merger.merge_at("left", vec![tag("abc", 3, -40)], now);
+        let weaker = merger.merge_at("right", vec![tag("abc", 1, -60)], now);
+        let stronger = merger.merge_at("right", vec![tag("abc", 1, -30)], now);
+
+        assert!(weaker.is_empty());
+        assert_eq!(stronger[0].antenna, 4);
+    }
+
+    #[test]
+    fn should_release_claim_after_conflict_window() {
+        let mut merger = merger();
+        let now = Instant::now();
+
+        merger.merge_at("left", vec![tag("abc", 3, -40)], now);
+        let tags = merger.merge_at("right", vec![tag("abc", 1, -60)], now + CONFLICT_WINDOW);
+
+        assert_eq!(tags[0].antenna, 4);
+    }
+}
@@ -1,10 +1,7 @@
 use crate::tags::Tag;
 
 use super::{ReaderError, ReaderErrorKind};
-use llrp::{
-    messages::{Keepalive, Message},
-    BinaryMessage, LLRPMessage,
-};
+use llrp::{enumerations::StatusCode, messages::Message, parameters::LLRPStatus, BinaryMessage};
 use std::io;
 
 pub fn write_message<W: io::Write>(
@@ -54,57 +51,56 @@ pub fn handle_new_message<S: io::Write>(message: Message, stream: S) -> Vec<Tag>
     vec
 }
 
-pub fn parse_message_and<S, T: LLRPMessage>(
-    mut stream: S,
-    closure: fn(message: &T) -> bool,
-) -> Result<T, ReaderError>
-where
-    S: io::Read + io::Write,
-    for<'a> &'a mut S: io::Read,
-{
-    let binary_message = read_message(&mut stream)?;
-
-    match &binary_message.to_message::<Keepalive>() {
-        Ok(_) => respond_to_keepalive(stream),
-        Err(_) => (),
-    }
-
-    let message = match binary_message.to_message::<T>() {
-        Ok(message) => message,
-        Err(err) => {
-            return Err(ReaderError {
-                kind: ReaderErrorKind::Unknown,
-                message: format!(
-                    "Message was not of the expected kind, but was {:?}. Original error: {}",
-                    binary_message.to_dynamic_message(),
-                    err.to_string()
-                ),
-            })
-        }
+/// Convert the `LLRPStatus` of a response into a `ReaderError`
+///
+/// The reader includes a description and (optionally) the field or parameter that caused the error.
+/// We keep these in our message, since they are the most useful information when debugging a reader.
+pub fn check_status(status: &LLRPStatus) -> Result<(), ReaderError> {
+    let kind = match status.status_code {
+        StatusCode::M_Success => return Ok(()),
+        StatusCode::M_UnsupportedMessage
+        | StatusCode::M_UnsupportedVersion
+        | StatusCode::M_UnsupportedParameter
+        | StatusCode::P_UnsupportedParameter => ReaderErrorKind::Unsupported,
+        StatusCode::M_ParameterError
+        | StatusCode::M_FieldError
+        | StatusCode::M_UnexpectedParameter
+        | StatusCode::M_MissingParameter
+        | StatusCode::M_DuplicateParameter
+        | StatusCode::M_OverflowParameter
+        | StatusCode::M_OverflowField
+        | StatusCode::M_UnknownParameter
+        | StatusCode::M_UnknownField
+        | StatusCode::P_ParameterError
+        | StatusCode::P_FieldError
+        | StatusCode::P_UnexpectedParameter
+        | StatusCode::P_MissingParameter
+        | StatusCode::P_DuplicateParameter
+        | StatusCode::P_OverflowParameter
+        | StatusCode::P_OverflowField
+        | StatusCode::P_UnknownParameter
+        | StatusCode::P_UnknownField => ReaderErrorKind::InvalidParameter,
+        StatusCode::A_Invalid | StatusCode::A_OutOfRange => ReaderErrorKind::InvalidArgument,
+        StatusCode::R_DeviceError => ReaderErrorKind::DeviceError,
+        #[allow(unreachable_patterns)]
+        _ => ReaderErrorKind::Unknown,
     };
 
-    match closure(&message) {
-        true => Ok(message),
-        false => Err(ReaderError {
-            kind: ReaderErrorKind::Unknown,
-            message: format!(
-                "Message did not pass closure check. Full message: {:?}",
-                binary_message.to_dynamic_message(),
-            ),
-        }),
+    let mut message = format!("{:?}: {}", status.status_code, status.error_description);
+    if let Some(error) = &status.field_error {
+        message.push_str(&format!(
+            " (field {}: {:?})",
+            error.field_num, error.error_code
+        ));
     }
-}
-
-fn read_message<S: io::Read>(stream: S) -> Result<BinaryMessage, ReaderError> {
-    match llrp::read_message(stream) {
-        Ok(message) => Ok(message),
-        Err(err) => {
-            return Err(ReaderError {
-                kind: ReaderErrorKind::Unknown,
-                message: err.to_string(),
-            })
-        }
+    if let Some(error) = &status.parameter_error {
+        message.push_str(&format!(
+            " (parameter type {}: {:?})",
+            error.parameter_type, error.error_code
+        ));
     }
+
+    Err(ReaderError { kind, message })
 }
 
 fn respond_to_keepalive<S: io::Write>(stream: S) {
@@ -152,9 +148,8 @@ pub fn construct_report_message(antenna_id: u16, peak_rssi: i8) -> Message {
 mod tests {
     use super::*;
     use llrp::{
-        enumerations::StatusCode,
         messages::{self, StopRospecResponse},
-        parameters::LLRPStatus,
+        parameters::{FieldError, ParameterError},
         LLRPMessage,
     };
     use std::io::Cursor;
@@ -208,4 +203,56 @@ mod tests {
 
         assert_eq!(raw.message_type, llrp::messages::KeepaliveAck::ID);
     }
+
+    #[test]
+    fn should_accept_successful_status() {
+        let status = LLRPStatus {
+            status_code: StatusCode::M_Success,
+            error_description: String::from(""),
+            field_error: None,
+            parameter_error: None,
+        };
+
+        assert!(check_status(&status).is_ok());
+    }
+
+    #[test]
+    fn should_map_unsupported_parameter_status() {
+        let status = LLRPStatus {
+            status_code: StatusCode::M_UnsupportedParameter,
+            error_description: String::from("Custom parameter not supported"),
+            field_error: None,
+            parameter_error: Some(ParameterError {
+                parameter_type: 1023,
+                error_code: StatusCode::P_UnsupportedParameter,
+                field_error: None,
+                parameter_error: None,
+            }),
+        };
+
+        let error = check_status(&status).unwrap_err();
+
+        assert_eq!(error.kind, ReaderErrorKind::Unsupported);
+        assert!(error.message.contains("Custom parameter not supported"));
+        assert!(error.message.contains("parameter type 1023"));
+    }
+
+    #[test]
+    fn should_map_device_error_status() {
+        let status = LLRPStatus {
+            status_code: StatusCode::R_DeviceError,
+            error_description: String::from("Reader is busy"),
+            field_error: Some(FieldError {
+                field_num: 2,
+                error_code: StatusCode::A_Invalid,
+            }),
+            parameter_error: None,
+        };
+
+        let error = check_status(&status).unwrap_err();
+
+        assert_eq!(error.kind, ReaderErrorKind::DeviceError);
+        assert!(error.message.contains("Reader is busy"));
+        assert!(error.message.contains("field 2"));
+    }
 }
@@ -1,57 +1,114 @@
-use std::{thread::sleep, time::Duration};
-
-use tauri::{
-    async_runtime::{spawn, JoinHandle},
-    AppHandle, Manager,
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, sleep, JoinHandle},
+    time::{Duration, Instant},
 };
 
-use crate::{tags::Tag, GlobalState};
+use crate::tags::Tag;
+
+use super::{
+    info::ReaderInfo,
+    merge::ReaderConnection,
+    scenario::{RandomGroup, Scenario, DEFAULT_GROUP_SIZE},
+    settings::ReaderSettings,
+    sink::ReaderSink,
+    ReaderError, ReaderErrorKind, ReaderProtocol, ReaderState, REFRESH_INTERVAL,
+};
 
-use super::{ReaderProtocol, REFRESH_INTERVAL};
+/// What kind of reads a `MockReader` sends
+#[derive(Debug, Clone)]
+enum MockMode {
+    /// Every read is a random tag on a random antenna
+    Chaos,
+    /// A group of real tokens that mostly stay in their circle
+    Random { seed: u64, group_size: usize },
+    /// Follow the timeline of a scenario file
+    Scenario(Scenario),
+}
 
 /// Create a MockReader
 ///
-/// This reader will sent a set of random tags
+/// By default this reader will sent a set of random tags. It supports the following options:
+/// * `scenario`: the path of a scenario file to play (see [`Scenario`])
+/// * `seed` and/or `group_size`: simulate a group of participants with real tokens, using the given seed
 #[derive(Debug)]
 pub struct MockReader {
+    mode: MockMode,
+    sink: ReaderSink,
+    stop: Arc<AtomicBool>,
     handle: Option<JoinHandle<()>>,
 }
 
-impl ReaderProtocol for MockReader {
-    fn new<R: tauri::Runtime>(
-        _hostname: String,
-        _app_handle: AppHandle<R>,
-    ) -> Result<Self, super::ReaderError>
-    where
-        Self: Sized,
-    {
-        Ok(MockReader { handle: None })
+impl MockReader {
+    pub fn new(connection: &ReaderConnection, sink: ReaderSink) -> Result<MockReader, ReaderError> {
+        let mode = match connection.options.get("scenario") {
+            Some(path) => MockMode::Scenario(Scenario::load(Path::new(path))?),
+            None if connection.options.contains_key("seed")
+                || connection.options.contains_key("group_size") =>
+            {
+                MockMode::Random {
+                    seed: parse_option(connection, "seed")?.unwrap_or_else(rand::random),
+                    group_size: parse_option(connection, "group_size")?
+                        .unwrap_or(DEFAULT_GROUP_SIZE),
+                }
+            }
+            None => MockMode::Chaos,
+        };
+
+        sink.state(ReaderState::Ready);
+        Ok(MockReader {
+            mode,
+            sink,
+            stop: Arc::new(AtomicBool::new(false)),
+            handle: None,
+        })
     }
+}
+
+fn parse_option<T: std::str::FromStr>(
+    connection: &ReaderConnection,
+    name: &str,
+) -> Result<Option<T>, ReaderError> {
+    connection
+        .options
+        .get(name)
+        .map(|value| {
+            value.parse().map_err(|_| ReaderError {
+                kind: ReaderErrorKind::CouldNotConnect(connection.hostname.clone()),
+                message: format!("Option {} has an invalid value {}", name, value),
+            })
+        })
+        .transpose()
+}
+
+impl ReaderProtocol for MockReader {
+    fn start_reading(&mut self) -> Result<(), ReaderError> {
+        self.stop_reading(true)?;
+        self.sink.state(ReaderState::Reading);
 
-    fn start_reading<R: tauri::Runtime>(
-        &mut self,
-        app_handle: AppHandle<R>,
-    ) -> Result<(), super::ReaderError> {
-        let handle = spawn(async move {
-            let tags_map = app_handle.state::<GlobalState>().tags_map.clone();
+        let sink = self.sink.clone();
+        let mode = self.mode.clone();
+        let stop = Arc::new(AtomicBool::new(false));
+        self.stop = stop.clone();
+        let handle = thread::spawn(move || {
             let sleep_duration = Duration::from_millis(REFRESH_INTERVAL.into());
-            loop {
-                let tags = vec![
-                    Tag::random(),
-                    Tag::random(),
-                    Tag::random(),
-                    Tag::random(),
-                    Tag::random(),
-                    Tag::random(),
-                    Tag::random(),
-                    Tag::random(),
-                    Tag::random(),
-                    Tag::random(),
-                ];
-                tags_map.lock().unwrap().add_tags(tags);
-                app_handle
-                    .emit_all("updated-tags", tags_map.lock().unwrap().clone())
-                    .unwrap();
+            // Every time we start reading, we start from the beginning (of the scenario or seed)
+            let started_at = Instant::now();
+            let mut group = match mode {
+                MockMode::Random { seed, group_size } => Some(RandomGroup::new(seed, group_size)),
+                _ => None,
+            };
+            while !stop.load(Ordering::Relaxed) {
+                let tags = match (&mode, &mut group) {
+                    (MockMode::Scenario(scenario), _) => scenario.tags_at(started_at.elapsed()),
+                    (_, Some(group)) => group.next_tags(),
+                    _ => (0..10).map(|_| Tag::random()).collect(),
+                };
+                sink.tags(tags);
                 sleep(sleep_duration)
             }
         });
@@ -59,10 +116,84 @@ impl ReaderProtocol for MockReader {
         Ok(())
     }
 
-    fn stop_reading(&mut self, _await_confirmation: bool) -> Result<(), super::ReaderError> {
+    fn configure(&mut self, _settings: ReaderSettings) -> Result<(), ReaderError> {
+        Ok(())
+    }
+
+    fn info(&self) -> Option<ReaderInfo> {
+        Some(ReaderInfo {
+            firmware_version: String::from("mock"),
+            antenna_count: 3,
+            connected_antennas: vec![1, 2, 3],
+            ..Default::default()
+        })
+    }
+
+    fn stop_reading(&mut self, await_confirmation: bool) -> Result<(), ReaderError> {
+        self.stop.store(true, Ordering::Relaxed);
         if let Some(handle) = self.handle.take() {
-            handle.abort();
+            if await_confirmation {
+                let _ = handle.join();
+            }
         };
         Ok(())
     }
 }
+
+impl Drop for MockReader {
+    fn drop(&mut self) {
+        let _ = self.stop_reading(false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::sink::ReaderEvent;
+
+    #[test]
+    fn should_send_tags_until_stopped() {
+        let (sink, rx) = ReaderSink::channel(String::from("mock"));
+        let mut reader =
+            MockReader::new(&ReaderConnection::new(String::from("mock")), sink).unwrap();
+
+        reader.start_reading().unwrap();
+        let events: Vec<ReaderEvent> = rx.iter().take(3).collect();
+        reader.stop_reading(true).unwrap();
+
+        assert!(matches!(events[0], ReaderEvent::State(ReaderState::Ready)));
+        assert!(matches!(
+            events[1],
+            ReaderEvent::State(ReaderState::Reading)
+        ));
+        assert!(matches!(&events[2], ReaderEvent::Tags(tags) if tags.len() == 10));
+        // Once stopped, no new tags arrive
+        while rx.try_recv().is_ok() {}
+        sleep(Duration::from_millis((REFRESH_INTERVAL * 2).into()));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn should_send_random_group_from_options() {
+        let mut connection = ReaderConnection::new(String::from("mock"));
+        connection.options = [
+            (String::from("seed"), String::from("42")),
+            (String::from("group_size"), String::from("4")),
+        ]
+        .into();
+        let (sink, rx) = ReaderSink::channel(String::from("mock"));
+        let mut reader = MockReader::new(&connection, sink).unwrap();
+
+        reader.start_reading().unwrap();
+        let tags = rx
+            .iter()
+            .find_map(|event| match event {
+                ReaderEvent::Tags(tags) => Some(tags),
+                _ => None,
+            })
+            .unwrap();
+        reader.stop_reading(true).unwrap();
+
+        assert_eq!(tags.len(), 4);
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+
+use super::{
+    hid_reader::HidReader, merge::ReaderConnection, serial_reader::SerialReader,
+    settings::ReaderSettings, sink::ReaderSink, tls::TlsOptions, LLRPReader, MockReader, Reader,
+    ReaderError, ReaderErrorKind,
+};
+
+/// Connects to a reader of a single backend
+pub type ReaderConstructor =
+    fn(&ReaderConnection, ReaderSettings, ReaderSink) -> Result<Reader, ReaderError>;
+
+/// The backend we use when a connection does not specify one
+pub const DEFAULT_BACKEND: &str = "llrp";
+
+/// All reader backends we know, by name
+///
+/// Adding support for new hardware means implementing `ReaderProtocol` in its own module
+/// and registering its constructor here.
+#[derive(Debug, Clone)]
+pub struct ReaderRegistry {
+    backends: BTreeMap<&'static str, ReaderConstructor>,
+}
+
+impl Default for ReaderRegistry {
+    fn default() -> Self {
+        let mut registry = ReaderRegistry::empty();
+        registry.register("llrp", |connection, settings, sink| {
+            Ok(Box::new(LLRPReader::new(
+                connection.hostname.clone(),
+                TlsOptions::from_options(&connection.options),
+                settings,
+                sink,
+            )?))
+        });
+        registry.register("mock", |connection, _, sink| {
+            Ok(Box::new(MockReader::new(connection, sink)?))
+        });
+        registry.register("serial", |connection, _, sink| {
+            Ok(Box::new(SerialReader::new(connection, sink)?))
+        });
+        registry.register("hid", |connection, _, sink| {
+            Ok(Box::new(HidReader::new(connection, sink)?))
+        });
+        registry
+    }
+}
+
+impl ReaderRegistry {
+    pub fn empty() -> ReaderRegistry {
+        ReaderRegistry {
+            backends: BTreeMap::new(),
+        }
+    }
+
+    pub fn register(&mut self, name: &'static str, constructor: ReaderConstructor) {
+        self.backends.insert(name, constructor);
+    }
+
+    pub fn backends(&self) -> Vec<&'static str> {
+        self.backends.keys().copied().collect()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.backends.contains_key(name)
+    }
+
+    /// Connect to a reader, using the backend of its connection
+    pub fn connect(
+        &self,
+        connection: &ReaderConnection,
+        settings: ReaderSettings,
+        sink: ReaderSink,
+    ) -> Result<Reader, ReaderError> {
+        let Some(constructor) = self.backends.get(connection.backend.as_str()) else {
+            return Err(ReaderError {
+                kind: ReaderErrorKind::UnknownBackend(connection.backend.clone()),
+                message: format!("Available backends are {:?}", self.backends()),
+            });
+        };
+        constructor(connection, settings, sink)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_connect_to_registered_backend() {
+        let registry = ReaderRegistry::default();
+        let mut connection = ReaderConnection::new(String::from("mock"));
+        connection.backend = String::from("mock");
+        let (sink, _rx) = ReaderSink::channel(connection.hostname.clone());
+
+        let reader = registry.connect(&connection, ReaderSettings::default(), sink);
+
+        assert!(reader.is_ok_and(|reader| reader.info().is_some()));
+    }
+
+    #[test]
+    fn should_err_on_unknown_backend() {
+        let registry = ReaderRegistry::default();
+        let mut connection = ReaderConnection::new(String::from("reader"));
+        connection.backend = String::from("carrier-pigeon");
+        let (sink, _rx) = ReaderSink::channel(connection.hostname.clone());
+
+        let result = registry.connect(&connection, ReaderSettings::default(), sink);
+
+        assert!(result.is_err_and(
+            |err| err.kind == ReaderErrorKind::UnknownBackend(String::from("carrier-pigeon"))
+        ));
+    }
+}
@@ -1,24 +1,32 @@
 use llrp::{choices, enumerations, parameters, Encoder, LLRPValue};
 
-use super::DEFAULT_ROSPEC_ID;
+use super::{
+    settings::{ReaderSettings, ReportMode, DEFAULT_ANTENNAS},
+    DEFAULT_ROSPEC_ID,
+};
 
-pub fn construct_default_rospec() -> parameters::ROSpec {
-    let moto_antenna_stop_condition_param = parameters::Custom {
-        // This is the MotoAntennaStopCondition from Zebra's custom extensions
-        // See their manual page 183
-        vendor_identifier: 161,
-        parameter_subtype: 704,
-        // This sets the following values:
-        // * AntennaStopTrigger: Set the trigger to `DwellTime` (0) or `NumberInventoryCycles` (1)
-        // * AntennaStopConditionValue: Set the associated value in milliseconds or number of cycles
-        // This is represented in an u8 vec, where the first byte represent the trigger and the next two bytes the value
-        data: vec![0, 0, 250],
+pub fn construct_rospec(settings: &ReaderSettings) -> parameters::ROSpec {
+    let (antenna_ids, antenna_configuration) = if settings.transmit_power.is_empty() {
+        // Antenna ID 0 means this applies to all antennas
+        // We set the transmit power to its lowest possible value
+        (
+            DEFAULT_ANTENNAS.to_vec(),
+            vec![construct_antenna_configuration(0, 0)],
+        )
+    } else {
+        (
+            settings.transmit_power.keys().copied().collect(),
+            settings
+                .transmit_power
+                .iter()
+                .map(|(&antenna_id, &transmit_power)| {
+                    construct_antenna_configuration(antenna_id, transmit_power)
+                })
+                .collect(),
+        )
     };
-    let mut encoded_moto_antenna_stop_condition_param: Vec<u8> = vec![];
-    let mut encoder = Encoder::new(&mut encoded_moto_antenna_stop_condition_param);
-    moto_antenna_stop_condition_param.encode(&mut encoder);
 
-    parameters::ROSpec {
+    let mut rospec = parameters::ROSpec {
       ro_spec_id: DEFAULT_ROSPEC_ID,
       priority: 0,
       current_state: enumerations::ROSpecState::Disabled, // Setting this to `Inactive` or `Active` results in an error from our reader
@@ -36,7 +44,7 @@ pub fn construct_default_rospec() -> parameters::ROSpec {
           },
       },
       spec_parameter: vec![choices::SpecParameter::AISpec(parameters::AISpec {
-          antenna_ids: vec![1, 2, 3],
+          antenna_ids,
           ai_spec_stop_trigger: parameters::AISpecStopTrigger {
               ai_spec_stop_trigger_type: enumerations::AISpecStopTriggerType::Null,
               duration_trigger: 0,
@@ -46,44 +54,7 @@ pub fn construct_default_rospec() -> parameters::ROSpec {
           inventory_parameter_spec: vec![parameters::InventoryParameterSpec {
               inventory_parameter_spec_id: 1,
               protocol_id: enumerations::AirProtocols::EPCGlobalClass1Gen2,
-              antenna_configuration: vec![parameters::AntennaConfiguration {
-                antenna_id: 0, // Antenna ID 0 means this applies to all antennas
-                rf_receiver: None,
-                rf_transmitter: Some(parameters::RFTransmitter {
-                    // We don't use frequency hopping, but have to set a value. `1` seems to work on all of our readers
-                    hop_table_id: 1,
-                    // Our reader come with one fixed frequency. this index is one based
-                    channel_index: 1,
-                    // Our readers come with a table that contains 192 entries, going from 10dBm to 29.2dBm in increments of 0.1dBm
-                    // We set the transmit power to its lowest possible value 
-                    transmit_power: 0,
-                }),
-                air_protocol_inventory_command_settings: vec![
-                    choices::AirProtocolInventoryCommandSettings::C1G2InventoryCommand(
-                    parameters::C1G2InventoryCommand {
-                        tag_inventory_state_aware: false,
-                        reserved: 0,
-                        c1g2_filter: Vec::new(),
-                        c1g2_rf_control: None,
-                        c1g2_singulation_control: None,
-                        custom: vec![
-                            // We use custom params to control the dwell time of the antennas. This needs to be set as part of a C1G2InventoryCommand
-                            // according to the Zebra manual.
-                            // Note that setting the wrong custom parameter at the wrong location (while still providing a valid configuration)
-                            // often results in the reader freezing up and not returning any messages until a force-reboot.
-                            parameters::Custom {
-                                // This is the `MotoAntennaConfig` from Zebra's custom extensions
-                                // See page 183 of their manual
-                                // This wraps our `MotoAntennaStopCondition`
-                                // It also supports other optional parameters (which we don't use)
-                                vendor_identifier: 161,
-                                parameter_subtype: 703,
-                                data: encoded_moto_antenna_stop_condition_param
-                            }
-                        ],
-                    })
-                ]
-              }],
+              antenna_configuration,
               custom: Vec::new(),
           }],
           custom: Vec::new(),
@@ -110,5 +81,130 @@ pub fn construct_default_rospec() -> parameters::ROSpec {
           },
           custom: Vec::new(),
       }),
-  }
+  };
+
+    if settings.report_mode == ReportMode::Polled {
+        // The reader keeps its reports until we ask for them
+        if let Some(report_spec) = rospec.ro_report_spec.as_mut() {
+            report_spec.ro_report_trigger = enumerations::ROReportTriggerType::None;
+            report_spec.n = 0;
+        }
+    }
+    rospec
+}
+
+fn construct_antenna_configuration(
+    antenna_id: u16,
+    transmit_power: u16,
+) -> parameters::AntennaConfiguration {
+    let moto_antenna_stop_condition_param = parameters::Custom {
+        // This is the MotoAntennaStopCondition from Zebra's custom extensions
+        // See their manual page 183
+        vendor_identifier: 161,
+        parameter_subtype: 704,
+        // This sets the following values:
+        // * AntennaStopTrigger: Set the trigger to `DwellTime` (0) or `NumberInventoryCycles` (1)
+        // * AntennaStopConditionValue: Set the associated value in milliseconds or number of cycles
+        // This is represented in an u8 vec, where the first byte represent the trigger and the next two bytes the value
+        data: vec![0, 0, 250],
+    };
+    let mut encoded_moto_antenna_stop_condition_param: Vec<u8> = vec![];
+    let mut encoder = Encoder::new(&mut encoded_moto_antenna_stop_condition_param);
+    moto_antenna_stop_condition_param.encode(&mut encoder);
+
+    parameters::AntennaConfiguration {
+        antenna_id,
+        rf_receiver: None,
+        rf_transmitter: Some(parameters::RFTransmitter {
+            // We don't use frequency hopping, but have to set a value. `1` seems to work on all of our readers
+            hop_table_id: 1,
+            // Our reader come with one fixed frequency. this index is one based
+            channel_index: 1,
+            // Our readers come with a table that contains 192 entries, going from 10dBm to 29.2dBm in increments of 0.1dBm
+            transmit_power,
+        }),
+        air_protocol_inventory_command_settings: vec![
+            choices::AirProtocolInventoryCommandSettings::C1G2InventoryCommand(
+                parameters::C1G2InventoryCommand {
+                    tag_inventory_state_aware: false,
+                    reserved: 0,
+                    c1g2_filter: Vec::new(),
+                    c1g2_rf_control: None,
+                    c1g2_singulation_control: None,
+                    custom: vec![
+                        // We use custom params to control the dwell time of the antennas. This needs to be set as part of a C1G2InventoryCommand
+                        // according to the Zebra manual.
+                        // Note that setting the wrong custom parameter at the wrong location (while still providing a valid configuration)
+                        // often results in the reader freezing up and not returning any messages until a force-reboot.
+                        parameters::Custom {
+                            // This is the `MotoAntennaConfig` from Zebra's custom extensions
+                            // See page 183 of their manual
+                            // This wraps our `MotoAntennaStopCondition`
+                            // It also supports other optional parameters (which we don't use)
+                            vendor_identifier: 161,
+                            parameter_subtype: 703,
+                            data: encoded_moto_antenna_stop_condition_param,
+                        },
+                    ],
+                },
+            ),
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn antenna_settings(rospec: &parameters::ROSpec) -> (Vec<u16>, Vec<(u16, u16)>) {
+        let choices::SpecParameter::AISpec(spec) = &rospec.spec_parameter[0] else {
+            panic!("Expected an AISpec");
+        };
+        let powers = spec.inventory_parameter_spec[0]
+            .antenna_configuration
+            .iter()
+            .map(|config| {
+                (
+                    config.antenna_id,
+                    config.rf_transmitter.as_ref().unwrap().transmit_power,
+                )
+            })
+            .collect();
+        (spec.antenna_ids.clone(), powers)
+    }
+
+    #[test]
+    fn should_use_all_antennas_at_lowest_power_by_default() {
+        let rospec = construct_rospec(&ReaderSettings::default());
+
+        assert_eq!(antenna_settings(&rospec), (vec![1, 2, 3], vec![(0, 0)]));
+    }
+
+    #[test]
+    fn should_use_transmit_power_per_antenna() {
+        let settings = ReaderSettings {
+            transmit_power: [(1, 20), (3, 40)].into(),
+            ..Default::default()
+        };
+        let rospec = construct_rospec(&settings);
+
+        assert_eq!(
+            antenna_settings(&rospec),
+            (vec![1, 3], vec![(1, 20), (3, 40)])
+        );
+    }
+
+    #[test]
+    fn should_buffer_reports_when_polling() {
+        let settings = ReaderSettings {
+            report_mode: ReportMode::Polled,
+            ..Default::default()
+        };
+        let report_spec = construct_rospec(&settings).ro_report_spec.unwrap();
+
+        assert_eq!(
+            report_spec.ro_report_trigger,
+            enumerations::ROReportTriggerType::None
+        );
+    }
 }
@@ -0,0 +1,219 @@
+use std::{collections::BTreeMap, fs, path::Path, time::Duration};
+
+use rand::{rngs::StdRng, seq::IndexedRandom, Rng, SeedableRng};
+
+use crate::tags::{tokens, Tag};
+
+use super::{settings::DEFAULT_ANTENNAS, ReaderError, ReaderErrorKind};
+
+/// The group size of the random mode if it is not configured
+pub const DEFAULT_GROUP_SIZE: usize = 10;
+/// The RSSI of a scenario event that doesn't specify one
+const DEFAULT_RSSI: i8 = -45;
+/// The chance that a participant moves their token to another circle on every read
+const MOVE_CHANCE: f64 = 0.01;
+/// How much the RSSI of a token varies between two reads
+const RSSI_JITTER: i8 = 5;
+
+/// A scripted timeline of tokens that join, move between and leave the circles
+///
+/// ```json
+/// {
+///   "repeat": true,
+///   "events": [
+///     { "at": 0, "token": "E2004702E4E16828021E62FE", "antenna": 1, "rssi": -40 },
+///     { "at": 2000, "token": "E2004702E4E16828021E62FE", "antenna": 2 },
+///     { "at": 5000, "token": "E2004702E4E16828021E62FE" }
+///   ]
+/// }
+/// ```
+///
+/// `at` is the number of milliseconds since the start of reading. An event without antenna removes the token.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+pub struct Scenario {
+    /// Start over once the last event has passed
+    #[serde(default)]
+    pub repeat: bool,
+    pub events: Vec<ScenarioEvent>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+pub struct ScenarioEvent {
+    pub at: u64,
+    pub token: String,
+    #[serde(default)]
+    pub antenna: Option<u16>,
+    #[serde(default = "default_rssi")]
+    pub rssi: i8,
+}
+
+fn default_rssi() -> i8 {
+    DEFAULT_RSSI
+}
+
+impl Scenario {
+    pub fn load(path: &Path) -> Result<Scenario, ReaderError> {
+        let error = |message: String| ReaderError {
+            kind: ReaderErrorKind::CouldNotConnect(path.display().to_string()),
+            message,
+        };
+        let content = fs::read_to_string(path).map_err(|err| error(err.to_string()))?;
+        let mut scenario: Scenario =
+            serde_json::from_str(&content).map_err(|err| error(err.to_string()))?;
+        scenario.events.sort_by_key(|event| event.at);
+        Ok(scenario)
+    }
+
+    /// The tokens that are in a circle at the given time since the start
+    pub fn tags_at(&self, elapsed: Duration) -> Vec<Tag> {
+        let mut elapsed = elapsed.as_millis() as u64;
+        let duration = self.events.last().map(|event| event.at).unwrap_or(0);
+        if self.repeat && duration > 0 {
+            elapsed %= duration + 1;
+        }
+
+        let mut present: BTreeMap<&str, (u16, i8)> = BTreeMap::new();
+        for event in self.events.iter().take_while(|event| event.at <= elapsed) {
+            match event.antenna {
+                Some(antenna) => present.insert(&event.token, (antenna, event.rssi)),
+                None => present.remove(event.token.as_str()),
+            };
+        }
+
+        present
+            .into_iter()
+            .filter_map(|(token, (antenna, rssi))| {
+                Tag::build(token.to_string(), antenna, rssi).ok()
+            })
+            .collect()
+    }
+}
+
+/// A group of participants with real tokens, who occasionally move their token to another circle
+///
+/// Using the same seed always results in the same reads, which keeps demos and tests reproducible.
+#[derive(Debug)]
+pub struct RandomGroup {
+    rng: StdRng,
+    /// The circle and base RSSI of every token in the group
+    tokens: BTreeMap<String, (u16, i8)>,
+}
+
+impl RandomGroup {
+    pub fn new(seed: u64, group_size: usize) -> RandomGroup {
+        let mut rng = StdRng::seed_from_u64(seed);
+        // The token list is a HashMap, so we sort it to make sure the seed always picks the same tokens
+        let mut all_tokens: Vec<String> = tokens().into_keys().collect();
+        all_tokens.sort();
+
+        let tokens = all_tokens
+            .choose_multiple(&mut rng, group_size)
+            .cloned()
+            .collect::<Vec<String>>()
+            .into_iter()
+            .map(|token| {
+                let antenna = *DEFAULT_ANTENNAS.choose(&mut rng).unwrap();
+                let rssi = rng.random_range(-65..=-35);
+                (token, (antenna, rssi))
+            })
+            .collect();
+
+        RandomGroup { rng, tokens }
+    }
+
+    pub fn next_tags(&mut self) -> Vec<Tag> {
+        let mut tags = Vec::with_capacity(self.tokens.len());
+        for (token, (antenna, rssi)) in self.tokens.iter_mut() {
+            if self.rng.random_bool(MOVE_CHANCE) {
+                *antenna = *DEFAULT_ANTENNAS.choose(&mut self.rng).unwrap();
+            }
+            let jitter = self.rng.random_range(-RSSI_JITTER..=RSSI_JITTER);
+            tags.push(Tag::new(
+                token.clone(),
+                *antenna,
+                rssi.saturating_add(jitter),
+            ));
+        }
+        tags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(at: u64, token: &str, antenna: Option<u16>) -> ScenarioEvent {
+        ScenarioEvent {
+            at,
+            token: String::from(token),
+            antenna,
+            rssi: DEFAULT_RSSI,
+        }
+    }
+
+    fn antennas(tags: Vec<Tag>) -> Vec<(String, u16)> {
+        tags.into_iter().map(|tag| (tag.id, tag.antenna)).collect()
+    }
+
+    #[test]
+    fn should_follow_scenario_timeline() {
+        let scenario = Scenario {
+            repeat: false,
+            events: vec![
+                event(0, "A", Some(1)),
+                event(100, "B", Some(2)),
+                event(200, "A", Some(3)),
+                event(300, "B", None),
+            ],
+        };
+
+        assert_eq!(
+            antennas(scenario.tags_at(Duration::from_millis(150))),
+            vec![(String::from("A"), 1), (String::from("B"), 2)]
+        );
+        assert_eq!(
+            antennas(scenario.tags_at(Duration::from_millis(1000))),
+            vec![(String::from("A"), 3)]
+        );
+    }
+
+    #[test]
+    fn should_repeat_scenario() {
+        let scenario = Scenario {
+            repeat: true,
+            events: vec![event(0, "A", Some(1)), event(100, "A", None)],
+        };
+
+        assert_eq!(scenario.tags_at(Duration::from_millis(150)).len(), 1);
+    }
+
+    #[test]
+    fn should_parse_scenario_with_defaults() {
+        let scenario: Scenario =
+            serde_json::from_str(r#"{ "events": [{ "at": 10, "token": "A" }] }"#).unwrap();
+
+        assert_eq!(scenario.events, vec![event(10, "A", None)]);
+        assert!(!scenario.repeat);
+    }
+
+    #[test]
+    fn should_draw_same_group_from_same_seed() {
+        let mut first = RandomGroup::new(42, 5);
+        let mut second = RandomGroup::new(42, 5);
+
+        for _ in 0..10 {
+            assert_eq!(antennas(first.next_tags()), antennas(second.next_tags()));
+        }
+    }
+
+    #[test]
+    fn should_draw_group_from_token_list() {
+        let mut group = RandomGroup::new(7, 4);
+        let known_tokens = tokens();
+
+        let tags = group.next_tags();
+
+        assert_eq!(tags.len(), 4);
+        assert!(tags.iter().all(|tag| known_tokens.contains_key(&tag.id)));
+    }
+}
@@ -0,0 +1,423 @@
+use std::{
+    io::{self, Read, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+use serialport::SerialPort;
+
+use crate::tags::Tag;
+
+use super::{
+    info::ReaderInfo,
+    merge::ReaderConnection,
+    settings::{ReaderSettings, DEFAULT_ANTENNAS},
+    sink::ReaderSink,
+    ReaderError, ReaderErrorKind, ReaderProtocol, ReaderState, RECV_TIMEOUT,
+};
+
+const DEFAULT_BAUD_RATE: u32 = 115_200;
+/// These readers only have a single antenna, so we report every read on this antenna unless configured otherwise
+const DEFAULT_ANTENNA: u16 = 1;
+
+const FRAME_HEADER: u8 = 0xBB;
+const FRAME_END: u8 = 0x7E;
+const FRAME_TYPE_COMMAND: u8 = 0x00;
+const FRAME_TYPE_NOTIFICATION: u8 = 0x02;
+const COMMAND_INVENTORY: u8 = 0x22;
+const COMMAND_START_POLLING: u8 = 0x27;
+const COMMAND_STOP_POLLING: u8 = 0x28;
+/// Header, type, command, two bytes of payload length, checksum and end
+const FRAME_OVERHEAD: usize = 7;
+/// The longest payload an R200 sends, which is an inventory notification with the longest EPC (62 bytes)
+const MAX_PAYLOAD_LENGTH: usize = 67;
+
+/// Interact with a cheap USB (serial) UHF reader
+///
+/// These readers (e.g. based on the R200 module) use a simple framed protocol:
+/// `BB | type | command | length (2 bytes) | payload | checksum | 7E`, where the checksum is the lowest byte
+/// of the sum of everything between the header and the checksum.
+/// While polling, the reader sends a notification for every tag it sees, containing its RSSI, PC, EPC and CRC.
+///
+/// The hostname of the connection is the path of the device (e.g. `/dev/ttyUSB0`).
+/// Supported options are `baud_rate` and `antenna` (the antenna we report the reads on).
+#[derive(Debug)]
+pub struct SerialReader {
+    path: String,
+    antenna: u16,
+    port: Box<dyn SerialPort>,
+    sink: ReaderSink,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl SerialReader {
+    pub fn new(connection: &ReaderConnection, sink: ReaderSink) -> Result<Self, ReaderError> {
+        let path = connection.hostname.clone();
+        let baud_rate = parse_option(connection, "baud_rate", DEFAULT_BAUD_RATE)?;
+        let antenna = parse_option(connection, "antenna", DEFAULT_ANTENNA)?;
+        if !DEFAULT_ANTENNAS.contains(&antenna) {
+            return Err(ReaderError {
+                kind: ReaderErrorKind::CouldNotConnect(path),
+                message: format!("Option antenna has an invalid value {}", antenna),
+            });
+        }
+
+        sink.state(ReaderState::Connecting);
+        let port = serialport::new(&path, baud_rate)
+            .open()
+            .map_err(|err| ReaderError {
+                kind: ReaderErrorKind::CouldNotConnect(path.clone()),
+                message: err.to_string(),
+            })?;
+
+        Ok(SerialReader::from_port(path, port, antenna, sink))
+    }
+
+    /// Use a port that is already open
+    pub fn from_port(
+        path: String,
+        mut port: Box<dyn SerialPort>,
+        antenna: u16,
+        sink: ReaderSink,
+    ) -> SerialReader {
+        // We don't want to block forever while reading, so we can stop reading in time
+        let _ = port.set_timeout(RECV_TIMEOUT);
+        sink.state(ReaderState::Ready);
+        SerialReader {
+            path,
+            antenna,
+            port,
+            sink,
+            stop: Arc::new(AtomicBool::new(false)),
+            handle: None,
+        }
+    }
+
+    fn write_command(&mut self, command: u8, payload: &[u8]) -> Result<(), ReaderError> {
+        self.port
+            .write_all(&encode_frame(FRAME_TYPE_COMMAND, command, payload))
+            .map_err(|err| ReaderError {
+                kind: ReaderErrorKind::LostConnection,
+                message: err.to_string(),
+            })
+    }
+}
+
+impl ReaderProtocol for SerialReader {
+    fn start_reading(&mut self) -> Result<(), ReaderError> {
+        self.stop_reading(true)?;
+
+        let mut port = self.port.try_clone().map_err(|err| ReaderError {
+            kind: ReaderErrorKind::CouldNotConnect(self.path.clone()),
+            message: err.to_string(),
+        })?;
+        // Poll "forever" (0xFFFF rounds), we stop polling explicitly
+        self.write_command(COMMAND_START_POLLING, &[COMMAND_INVENTORY, 0xFF, 0xFF])?;
+
+        let sink = self.sink.clone();
+        let antenna = self.antenna;
+        let stop = Arc::new(AtomicBool::new(false));
+        self.stop = stop.clone();
+        self.handle = Some(thread::spawn(move || {
+            let mut parser = FrameParser::default();
+            let mut buffer = [0u8; 256];
+            while !stop.load(Ordering::Relaxed) {
+                match port.read(&mut buffer) {
+                    Ok(count) => {
+                        let tags = parser
+                            .push(&buffer[..count])
+                            .into_iter()
+                            .filter_map(|read| Tag::build(read.epc, antenna, read.rssi).ok())
+                            .collect();
+                        sink.tags(tags);
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::TimedOut => (),
+                    Err(err) => {
+                        sink.state(ReaderState::Failed);
+                        sink.error(ReaderError {
+                            kind: ReaderErrorKind::LostConnection,
+                            message: err.to_string(),
+                        });
+                        break;
+                    }
+                }
+            }
+        }));
+        self.sink.state(ReaderState::Reading);
+        Ok(())
+    }
+
+    fn configure(&mut self, _settings: ReaderSettings) -> Result<(), ReaderError> {
+        // These readers have a fixed power and a single antenna, so there is nothing to configure.
+        // The minimum RSSI is applied for every reader when adding tags.
+        Ok(())
+    }
+
+    fn info(&self) -> Option<ReaderInfo> {
+        Some(ReaderInfo {
+            reader_id: Some(self.path.clone()),
+            firmware_version: String::from("serial"),
+            antenna_count: 1,
+            connected_antennas: vec![self.antenna],
+            ..Default::default()
+        })
+    }
+
+    fn stop_reading(&mut self, await_confirmation: bool) -> Result<(), ReaderError> {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            self.write_command(COMMAND_STOP_POLLING, &[])?;
+            if await_confirmation {
+                let _ = handle.join();
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SerialReader {
+    fn drop(&mut self) {
+        let _ = self.stop_reading(false);
+    }
+}
+
+fn parse_option<T: std::str::FromStr>(
+    connection: &ReaderConnection,
+    name: &str,
+    default: T,
+) -> Result<T, ReaderError> {
+    match connection.options.get(name) {
+        None => Ok(default),
+        Some(value) => value.parse().map_err(|_| ReaderError {
+            kind: ReaderErrorKind::CouldNotConnect(connection.hostname.clone()),
+            message: format!("Option {} has an invalid value {}", name, value),
+        }),
+    }
+}
+
+pub fn encode_frame(frame_type: u8, command: u8, payload: &[u8]) -> Vec<u8> {
+    let length = (payload.len() as u16).to_be_bytes();
+    let mut frame = vec![FRAME_HEADER, frame_type, command, length[0], length[1]];
+    frame.extend_from_slice(payload);
+    frame.push(checksum(&frame[1..]));
+    frame.push(FRAME_END);
+    frame
+}
+
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+}
+
+/// A single tag seen by a serial reader
+#[derive(Debug, Clone, PartialEq)]
+pub struct SerialRead {
+    pub epc: String,
+    pub rssi: i8,
+}
+
+/// Splits the bytes we receive from a serial reader into frames
+///
+/// Reads from a serial port can contain partial frames, so we keep the remaining bytes until the rest arrives.
+/// Corrupt frames are skipped by searching for the next header. A length that no frame can have means the header
+/// was part of something else, so we never wait for the rest of such a frame.
+#[derive(Debug, Default)]
+pub struct FrameParser {
+    buffer: Vec<u8>,
+}
+
+impl FrameParser {
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<SerialRead> {
+        self.buffer.extend_from_slice(bytes);
+        let mut reads = Vec::new();
+
+        loop {
+            match self.buffer.iter().position(|&byte| byte == FRAME_HEADER) {
+                Some(start) => {
+                    self.buffer.drain(..start);
+                }
+                None => {
+                    self.buffer.clear();
+                    break;
+                }
+            }
+            if self.buffer.len() < FRAME_OVERHEAD {
+                break;
+            }
+
+            let payload_length = u16::from_be_bytes([self.buffer[3], self.buffer[4]]) as usize;
+            if payload_length > MAX_PAYLOAD_LENGTH {
+                self.buffer.drain(..1);
+                continue;
+            }
+            let frame_length = FRAME_OVERHEAD + payload_length;
+            if self.buffer.len() < frame_length {
+                break;
+            }
+
+            let frame: Vec<u8> = self.buffer[..frame_length].to_vec();
+            let is_valid = frame[frame_length - 1] == FRAME_END
+                && frame[frame_length - 2] == checksum(&frame[1..frame_length - 2]);
+            if !is_valid {
+                // This header was part of something else, try the next one
+                self.buffer.drain(..1);
+                continue;
+            }
+            self.buffer.drain(..frame_length);
+
+            if let Some(read) = parse_inventory(&frame) {
+                reads.push(read);
+            }
+        }
+        reads
+    }
+}
+
+/// Parse an inventory notification: `RSSI | PC (2 bytes) | EPC | CRC (2 bytes)`
+fn parse_inventory(frame: &[u8]) -> Option<SerialRead> {
+    if frame[1] != FRAME_TYPE_NOTIFICATION || frame[2] != COMMAND_INVENTORY {
+        return None;
+    }
+    let payload = &frame[5..frame.len() - 2];
+    if payload.len() < 5 {
+        return None;
+    }
+    let epc = payload[3..payload.len() - 2]
+        .iter()
+        .map(|byte| format!("{:02X?}", byte))
+        .collect();
+    Some(SerialRead {
+        epc,
+        rssi: payload[0] as i8,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::sink::ReaderEvent;
+
+    const EPC: [u8; 12] = [
+        0xE2, 0x00, 0x47, 0x02, 0xE4, 0xE1, 0x68, 0x28, 0x02, 0x1E, 0x62, 0xFE,
+    ];
+
+    fn inventory_frame(rssi: i8) -> Vec<u8> {
+        let mut payload = vec![rssi as u8, 0x30, 0x00];
+        payload.extend_from_slice(&EPC);
+        payload.extend_from_slice(&[0x12, 0x34]);
+        encode_frame(FRAME_TYPE_NOTIFICATION, COMMAND_INVENTORY, &payload)
+    }
+
+    #[test]
+    fn should_encode_start_command() {
+        let frame = encode_frame(
+            FRAME_TYPE_COMMAND,
+            COMMAND_START_POLLING,
+            &[0x22, 0x27, 0x10],
+        );
+
+        assert_eq!(
+            frame,
+            vec![0xBB, 0x00, 0x27, 0x00, 0x03, 0x22, 0x27, 0x10, 0x83, 0x7E]
+        );
+    }
+
+    #[test]
+    fn should_parse_inventory_frame() {
+        let mut parser = FrameParser::default();
+
+        let reads = parser.push(&inventory_frame(-55));
+
+        assert_eq!(
+            reads,
+            vec![SerialRead {
+                epc: String::from("E2004702E4E16828021E62FE"),
+                rssi: -55,
+            }]
+        );
+    }
+
+    #[test]
+    fn should_parse_frames_split_over_multiple_reads() {
+        let mut parser = FrameParser::default();
+        let frame = inventory_frame(-40);
+
+        assert!(parser.push(&frame[..6]).is_empty());
+        assert_eq!(parser.push(&frame[6..]).len(), 1);
+    }
+
+    #[test]
+    fn should_skip_garbage_and_corrupt_frames() {
+        let mut parser = FrameParser::default();
+        let mut corrupt = inventory_frame(-40);
+        let checksum_index = corrupt.len() - 2;
+        corrupt[checksum_index] ^= 0xFF;
+        // "No tag found" error frames are valid, but don't contain a tag
+        let error = encode_frame(0x01, 0xFF, &[0x15]);
+
+        let mut bytes = vec![0x01, 0x02];
+        bytes.extend(corrupt);
+        bytes.extend(error);
+        bytes.extend(inventory_frame(-50));
+        let reads = parser.push(&bytes);
+
+        assert_eq!(reads.len(), 1);
+        assert_eq!(reads[0].rssi, -50);
+    }
+
+    #[test]
+    fn should_not_wait_for_frames_that_are_too_long() {
+        let mut parser = FrameParser::default();
+
+        // A header with a length no frame can have, e.g. because of noise on the line
+        let mut bytes = vec![FRAME_HEADER, 0x02, 0x22, 0xFF, 0xFF];
+        bytes.extend(inventory_frame(-50));
+        let reads = parser.push(&bytes);
+
+        assert_eq!(reads.len(), 1);
+        assert_eq!(reads[0].rssi, -50);
+    }
+
+    #[test]
+    fn should_reject_antennas_without_a_circle() {
+        let (sink, _rx) = ReaderSink::channel(String::from("/dev/ttyUSB0"));
+        let mut connection = ReaderConnection::new(String::from("/dev/ttyUSB0"));
+        connection
+            .options
+            .insert(String::from("antenna"), String::from("4"));
+
+        let result = SerialReader::new(&connection, sink);
+
+        assert!(result.is_err_and(|err| err.message.contains("antenna")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn should_read_tags_from_serial_port() {
+        let (mut device, port) = serialport::TTYPort::pair().unwrap();
+        let (sink, rx) = ReaderSink::channel(String::from("pty"));
+        let mut reader = SerialReader::from_port(String::from("pty"), Box::new(port), 2, sink);
+
+        reader.start_reading().unwrap();
+        let frame = inventory_frame(-45);
+        device.write_all(&frame[..4]).unwrap();
+        device.flush().unwrap();
+        device.write_all(&frame[4..]).unwrap();
+
+        let tag = rx
+            .iter()
+            .find_map(|event| match event {
+                ReaderEvent::Tags(tags) => tags.into_iter().next(),
+                _ => None,
+            })
+            .unwrap();
+        reader.stop_reading(true).unwrap();
+
+        assert_eq!(tag.id, "E2004702E4E16828021E62FE");
+        assert_eq!(tag.antenna, 2);
+        assert_eq!(tag.strength, -45);
+    }
+}
@@ -0,0 +1,48 @@
+use std::collections::BTreeMap;
+
+/// The key we use to store the reader settings in the database
+pub const READER_SETTINGS_KEY: &str = "reader";
+/// The key we use to store the backend of the reader in the database
+pub const READER_BACKEND_KEY: &str = "reader_backend";
+/// The antennas we use when the settings don't specify any
+pub const DEFAULT_ANTENNAS: [u16; 3] = [1, 2, 3];
+
+/// Settings that are used to configure the reader
+///
+/// These are stored in the database, so they are kept when the application restarts.
+#[derive(Debug, Default, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ReaderSettings {
+    /// The transmit power index that should be used for every antenna.
+    ///
+    /// Only the antennas in this map will be used. If it is empty, we use all antennas at their lowest power.
+    #[serde(default)]
+    pub transmit_power: BTreeMap<u16, u16>,
+    /// The minimum RSSI (in dBm) a read should have, per option slot.
+    ///
+    /// Weaker reads are dropped before they reach our `TagsMap`. Slots that are not in this map accept every read.
+    #[serde(default)]
+    pub min_rssi: BTreeMap<u16, i8>,
+    /// How the reader sends us its reads
+    #[serde(default)]
+    pub report_mode: ReportMode,
+}
+
+/// How an LLRP reader sends us its reads
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ReportMode {
+    /// The reader sends a report for every read, which is the most responsive but results in a message per read
+    #[default]
+    Realtime,
+    /// The reader buffers its reads, and we ask for them with `GET_REPORT` every refresh interval
+    Polled,
+}
+
+impl ReaderSettings {
+    /// Use only a single antenna with the given power
+    pub fn single_antenna(antenna: u16, power_index: u16) -> ReaderSettings {
+        ReaderSettings {
+            transmit_power: BTreeMap::from([(antenna, power_index)]),
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use std::sync::{
+    mpsc::{channel, Receiver, Sender},
+    Arc, Mutex,
+};
+
+use crate::tags::Tag;
+
+use super::{ReaderError, ReaderState};
+
+/// Everything a reader backend reports while it is connected
+#[derive(Debug, Clone)]
+pub enum ReaderEvent {
+    /// New reads. This may be empty, which simply tells us the reader is still alive.
+    Tags(Vec<Tag>),
+    State(ReaderState),
+    /// An error that happened outside of a request, e.g. losing the connection while reading
+    Error(ReaderError),
+    /// An antenna of the reader was connected or disconnected
+    Antenna {
+        antenna: u16,
+        connected: bool,
+    },
+}
+
+type Callback = dyn Fn(&str, ReaderEvent) + Send + Sync;
+
+/// Where a reader backend sends its events
+///
+/// Backends don't know what happens with their events, which keeps them independent from Tauri.
+/// The application uses a sink that updates our `GlobalState` and the frontend, tests can use [`ReaderSink::channel`].
+#[derive(Clone)]
+pub struct ReaderSink {
+    reader_id: String,
+    callback: Arc<Callback>,
+}
+
+impl ReaderSink {
+    /// Call `callback` with the id of the reader for every event
+    pub fn new(
+        reader_id: String,
+        callback: impl Fn(&str, ReaderEvent) + Send + Sync + 'static,
+    ) -> ReaderSink {
+        ReaderSink {
+            reader_id,
+            callback: Arc::new(callback),
+        }
+    }
+
+    /// Collect all events in a channel
+    pub fn channel(reader_id: String) -> (ReaderSink, Receiver<ReaderEvent>) {
+        let (tx, rx): (Sender<ReaderEvent>, Receiver<ReaderEvent>) = channel();
+        let tx = Mutex::new(tx);
+        let sink = ReaderSink::new(reader_id, move |_, event| {
+            // If the receiver is gone, nobody is interested in our events anymore
+            let _ = tx.lock().unwrap().send(event);
+        });
+        (sink, rx)
+    }
+
+    pub fn reader_id(&self) -> &str {
+        &self.reader_id
+    }
+
+    pub fn send(&self, event: ReaderEvent) {
+        (self.callback)(&self.reader_id, event)
+    }
+
+    pub fn tags(&self, tags: Vec<Tag>) {
+        self.send(ReaderEvent::Tags(tags))
+    }
+
+    pub fn state(&self, state: ReaderState) {
+        self.send(ReaderEvent::State(state))
+    }
+
+    pub fn error(&self, error: ReaderError) {
+        self.send(ReaderEvent::Error(error))
+    }
+
+    pub fn antenna(&self, antenna: u16, connected: bool) {
+        self.send(ReaderEvent::Antenna { antenna, connected })
+    }
+}
+
+impl std::fmt::Debug for ReaderSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReaderSink")
+            .field("reader_id", &self.reader_id)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_send_events_with_reader_id() {
+        let (tx, rx) = channel();
+        let tx = Mutex::new(tx);
+        let sink = ReaderSink::new(String::from("left"), move |reader_id, event| {
+            tx.lock()
+                .unwrap()
+                .send((reader_id.to_string(), event))
+                .unwrap();
+        });
+
+        sink.state(ReaderState::Ready);
+
+        let (reader_id, event) = rx.recv().unwrap();
+        assert_eq!(reader_id, "left");
+        assert!(matches!(event, ReaderEvent::State(ReaderState::Ready)));
+    }
+}
@@ -0,0 +1,83 @@
+/// The state of our connection with a reader
+///
+/// Every change is emitted to the frontend as a `reader-state` event, so it can show a (translated) status.
+/// The current value of every reader is also kept in our `GlobalState` and can be requested with the
+/// `get_reader_state` (combined) and `get_reader_states` (per reader) commands.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum ReaderState {
+    /// There is no reader, or the connection was closed on purpose
+    #[default]
+    Disconnected,
+    /// We are opening a connection to the reader
+    Connecting,
+    /// The connection is open, but we are waiting for the reader to confirm the connection attempt
+    Handshaking,
+    /// We are resetting the reader and sending our configuration
+    Configuring,
+    /// The reader is configured, but is not reading tags
+    Ready,
+    /// The reader is reading and sending tags
+    Reading,
+    /// We are replacing an existing connection with a new one
+    Reconnecting,
+    /// Something went wrong. The details are sent separately as an `error` event
+    Failed,
+}
+
+impl ReaderState {
+    /// Combine the states of multiple readers into the state of the whole setup
+    ///
+    /// The setup is only as good as its worst reader, so we use the state that is the furthest from `Reading`.
+    pub fn combine<'a>(states: impl IntoIterator<Item = &'a ReaderState>) -> ReaderState {
+        states
+            .into_iter()
+            .copied()
+            .min_by_key(|state| state.rank())
+            .unwrap_or_default()
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            ReaderState::Failed => 0,
+            ReaderState::Disconnected => 1,
+            ReaderState::Reconnecting => 2,
+            ReaderState::Connecting => 3,
+            ReaderState::Handshaking => 4,
+            ReaderState::Configuring => 5,
+            ReaderState::Ready => 6,
+            ReaderState::Reading => 7,
+        }
+    }
+}
+
+/// The payload of the `reader-state` event
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ReaderStateChange {
+    pub reader: String,
+    pub state: ReaderState,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_default_to_disconnected() {
+        assert_eq!(ReaderState::default(), ReaderState::Disconnected);
+    }
+
+    #[test]
+    fn should_serialize_as_plain_string() {
+        let value = serde_json::to_string(&ReaderState::Handshaking).unwrap();
+
+        assert_eq!(value, "\"Handshaking\"");
+    }
+
+    #[test]
+    fn should_combine_into_worst_state() {
+        let states = [ReaderState::Reading, ReaderState::Configuring];
+
+        assert_eq!(ReaderState::combine(&states), ReaderState::Configuring);
+        assert_eq!(ReaderState::combine(&[]), ReaderState::Disconnected);
+    }
+}
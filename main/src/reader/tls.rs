@@ -0,0 +1,331 @@
+use std::{collections::BTreeMap, fs::File, io::BufReader, path::PathBuf, sync::Arc};
+
+use rustls::{
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider},
+    pki_types::{CertificateDer, ServerName, UnixTime},
+    ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+};
+
+use super::{ReaderError, ReaderErrorKind};
+
+/// The port LLRP uses for connections over TLS
+pub const TLS_PORT: u16 = 5085;
+
+/// Which certificates of the reader we trust
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum TlsTrust {
+    /// Certificates signed by one of the well-known public CAs
+    #[default]
+    PublicRoots,
+    /// Certificates signed by one of the CAs in this PEM file
+    CaFile(PathBuf),
+    /// Exactly the certificate(s) in this PEM file, e.g. the default certificate of a reader
+    Pinned(PathBuf),
+    /// Every certificate. The traffic is still encrypted, but we can't know who we are talking to.
+    AcceptSelfSigned,
+}
+
+/// How to connect to a reader over TLS
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TlsOptions {
+    pub trust: TlsTrust,
+    /// The name we expect in the certificate, if it isn't the hostname of the reader
+    pub server_name: Option<String>,
+}
+
+impl TlsOptions {
+    /// Read the TLS options from the options of a reader connection
+    ///
+    /// TLS is only used if `tls` is `true`. The trust is configured with `tls_ca`, `tls_cert` or `tls_accept_self_signed`.
+    pub fn from_options(options: &BTreeMap<String, String>) -> Option<TlsOptions> {
+        if options.get("tls").map(String::as_str) != Some("true") {
+            return None;
+        }
+
+        let trust = if let Some(path) = options.get("tls_cert") {
+            TlsTrust::Pinned(PathBuf::from(path))
+        } else if let Some(path) = options.get("tls_ca") {
+            TlsTrust::CaFile(PathBuf::from(path))
+        } else if options.get("tls_accept_self_signed").map(String::as_str) == Some("true") {
+            TlsTrust::AcceptSelfSigned
+        } else {
+            TlsTrust::PublicRoots
+        };
+
+        Some(TlsOptions {
+            trust,
+            server_name: options.get("tls_server_name").cloned(),
+        })
+    }
+
+    pub fn client_config(&self) -> Result<Arc<ClientConfig>, ReaderError> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let builder = ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(tls_error)?;
+
+        let config = match &self.trust {
+            TlsTrust::PublicRoots => {
+                let roots = RootCertStore {
+                    roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+                };
+                builder.with_root_certificates(roots).with_no_client_auth()
+            }
+            TlsTrust::CaFile(path) => {
+                let mut roots = RootCertStore::empty();
+                for certificate in load_certificates(path)? {
+                    roots.add(certificate).map_err(tls_error)?;
+                }
+                builder.with_root_certificates(roots).with_no_client_auth()
+            }
+            TlsTrust::Pinned(path) => builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(AcceptCertificates {
+                    certificates: Some(load_certificates(path)?),
+                    provider,
+                }))
+                .with_no_client_auth(),
+            TlsTrust::AcceptSelfSigned => builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(AcceptCertificates {
+                    certificates: None,
+                    provider,
+                }))
+                .with_no_client_auth(),
+        };
+        Ok(Arc::new(config))
+    }
+
+    /// The name we verify the certificate of the reader against
+    pub fn server_name(&self, hostname: &str) -> Result<ServerName<'static>, ReaderError> {
+        let name = self.server_name.as_deref().unwrap_or(hostname);
+        ServerName::try_from(name.to_string()).map_err(|err| ReaderError {
+            kind: ReaderErrorKind::IncorrectHostname(name.to_string()),
+            message: err.to_string(),
+        })
+    }
+}
+
+fn tls_error(err: impl ToString) -> ReaderError {
+    ReaderError {
+        kind: ReaderErrorKind::Unknown,
+        message: format!("Could not set up TLS: {}", err.to_string()),
+    }
+}
+
+fn load_certificates(path: &PathBuf) -> Result<Vec<CertificateDer<'static>>, ReaderError> {
+    let file = File::open(path).map_err(tls_error)?;
+    let certificates = rustls_pemfile::certs(&mut BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(tls_error)?;
+    if certificates.is_empty() {
+        return Err(tls_error(format!("No certificates in {}", path.display())));
+    }
+    Ok(certificates)
+}
+
+/// Accepts the given certificates (or every certificate) without checking who signed them
+///
+/// Readers come with a self-signed certificate, which no CA can vouch for.
+/// We still check that the reader owns the key of its certificate.
+#[derive(Debug)]
+struct AcceptCertificates {
+    /// Only accept these certificates. If this is `None`, we accept every certificate.
+    certificates: Option<Vec<CertificateDer<'static>>>,
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for AcceptCertificates {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        match &self.certificates {
+            Some(certificates)
+                if !certificates
+                    .iter()
+                    .any(|c| c.as_ref() == end_entity.as_ref()) =>
+            {
+                Err(rustls::Error::InvalidCertificate(
+                    rustls::CertificateError::UnknownIssuer,
+                ))
+            }
+            _ => Ok(ServerCertVerified::assertion()),
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::{
+        llrp_connection::LlrpConnection, messages::write_message, sink::ReaderSink,
+    };
+    use llrp::{
+        messages::{Keepalive, KeepaliveAck, Message},
+        LLRPMessage,
+    };
+    use rustls::{pki_types::PrivateKeyDer, ServerConfig};
+    use std::{
+        net::{SocketAddr, TcpListener, TcpStream},
+        thread::{self, JoinHandle},
+    };
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_rustls::TlsAcceptor;
+
+    /// Start a local TLS server with a self-signed certificate, which behaves like a reader
+    ///
+    /// It sends a keepalive and returns the message type of our response.
+    fn stand_in() -> (SocketAddr, String, JoinHandle<Option<u16>>) {
+        let rcgen::CertifiedKey { cert, key_pair } =
+            rcgen::generate_simple_self_signed(vec![String::from("localhost")]).unwrap();
+        let config =
+            ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+                .with_safe_default_protocol_versions()
+                .unwrap()
+                .with_no_client_auth()
+                .with_single_cert(
+                    vec![cert.der().clone()],
+                    PrivateKeyDer::Pkcs8(key_pair.serialize_der().into()),
+                )
+                .unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            stream.set_nonblocking(true).unwrap();
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            runtime.block_on(async move {
+                let stream = tokio::net::TcpStream::from_std(stream).unwrap();
+                let mut stream = TlsAcceptor::from(Arc::new(config))
+                    .accept(stream)
+                    .await
+                    .ok()?;
+
+                let mut keepalive = Vec::new();
+                write_message(&mut keepalive, Message::Keepalive(Keepalive {}), None).unwrap();
+                stream.write_all(&keepalive).await.ok()?;
+
+                let mut header = [0u8; 10];
+                stream.read_exact(&mut header).await.ok()?;
+                Some(u16::from_be_bytes([header[0], header[1]]) & 0x03FF)
+            })
+        });
+
+        (address, cert.pem(), server)
+    }
+
+    fn connect(address: SocketAddr, options: TlsOptions) -> Result<LlrpConnection, ReaderError> {
+        let stream = TcpStream::connect(address).unwrap();
+        let (sink, _) = ReaderSink::channel(String::from("localhost"));
+        LlrpConnection::spawn(
+            stream,
+            Some((options.client_config()?, options.server_name("localhost")?)),
+            sink,
+        )
+    }
+
+    #[test]
+    fn should_read_tls_options() {
+        let options: BTreeMap<String, String> = [
+            (String::from("tls"), String::from("true")),
+            (String::from("tls_accept_self_signed"), String::from("true")),
+        ]
+        .into();
+
+        assert_eq!(
+            TlsOptions::from_options(&options),
+            Some(TlsOptions {
+                trust: TlsTrust::AcceptSelfSigned,
+                server_name: None,
+            })
+        );
+        assert_eq!(TlsOptions::from_options(&BTreeMap::new()), None);
+    }
+
+    #[test]
+    fn should_talk_llrp_when_accepting_self_signed() {
+        let (address, _, server) = stand_in();
+        let options = TlsOptions {
+            trust: TlsTrust::AcceptSelfSigned,
+            server_name: None,
+        };
+
+        let connection = connect(address, options);
+
+        assert!(connection.is_ok());
+        assert_eq!(server.join().unwrap(), Some(KeepaliveAck::ID));
+    }
+
+    #[test]
+    fn should_accept_pinned_certificate() {
+        let (address, certificate, server) = stand_in();
+        let path = std::env::temp_dir().join(format!("circles-reader-{}.pem", address.port()));
+        std::fs::write(&path, certificate).unwrap();
+        let options = TlsOptions {
+            trust: TlsTrust::Pinned(path.clone()),
+            server_name: None,
+        };
+
+        let connection = connect(address, options);
+        std::fs::remove_file(path).unwrap();
+
+        assert!(connection.is_ok());
+        assert_eq!(server.join().unwrap(), Some(KeepaliveAck::ID));
+    }
+
+    #[test]
+    fn should_reject_untrusted_certificate() {
+        let (address, _, server) = stand_in();
+
+        let connection = connect(address, TlsOptions::default());
+
+        assert!(
+            connection.is_err_and(|err| matches!(err.kind, ReaderErrorKind::CouldNotConnect(_)))
+        );
+        assert_eq!(server.join().unwrap(), None);
+    }
+}
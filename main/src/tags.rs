@@ -3,6 +3,7 @@ use serde;
 use std::{
     collections::{hash_map::Values, HashMap},
     fmt::{Debug, Display, Formatter},
+    time::{SystemTime, UNIX_EPOCH},
     vec::Drain,
 };
 
@@ -10,6 +11,7 @@ const MAX_STRENGTH: i8 = 0;
 const MIN_STRENGTH: i8 = -80;
 const MAX_ANTENNA: u16 = 3;
 const MIN_ANTENNA: u16 = 1;
+const TOKEN_LIST: &str = include_str!("../../data/tokens/list.json");
 
 const MOCK_RFID_TAGS: [&str; 9] = [
     "E2004702E4E16828021E62FE",
@@ -23,11 +25,30 @@ const MOCK_RFID_TAGS: [&str; 9] = [
     "E2004702E4A16828021E62FA",
 ];
 
+/// All tokens we hand out, mapping their EPC to their type
+pub fn tokens() -> HashMap<String, String> {
+    serde_json::from_str(TOKEN_LIST).unwrap()
+}
+
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct Tag {
     pub id: String,
+    /// The RSSI of the latest read
     pub strength: i8,
     pub antenna: u16,
+    /// The strongest RSSI since the token arrived at its current antenna
+    pub peak_strength: i8,
+    /// When the token was first seen at its current antenna, in microseconds since the unix epoch
+    pub first_seen: u64,
+    /// When the token was last seen at its current antenna, in microseconds since the unix epoch
+    pub last_seen: u64,
+    /// How often the token was read since it arrived at its current antenna
+    pub read_count: u32,
+    /// When we first received a read of the token at its current antenna, in microseconds since the unix epoch
+    ///
+    /// Unlike `first_seen`, this always uses our own clock, so it can be compared with other times we keep.
+    #[serde(default)]
+    pub received_at: u64,
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -69,11 +90,24 @@ impl Tag {
             });
         }
 
-        Ok(Tag {
+        Ok(Tag::new(id, antenna, strength))
+    }
+
+    /// A single read of a token, seen right now
+    ///
+    /// Unlike [`Tag::build`], this does not check whether the antenna and strength are valid.
+    pub fn new(id: String, antenna: u16, strength: i8) -> Tag {
+        let now = now_micros();
+        Tag {
             id,
-            antenna,
             strength,
-        })
+            antenna,
+            peak_strength: strength,
+            first_seen: now,
+            last_seen: now,
+            read_count: 1,
+            received_at: now,
+        }
     }
 
     pub fn random() -> Tag {
@@ -81,12 +115,25 @@ impl Tag {
         let id = MOCK_RFID_TAGS.choose(&mut rng).unwrap().to_string();
         let antenna = rng.random_range(MIN_ANTENNA..=MAX_ANTENNA);
         let strength = rng.random_range(MIN_STRENGTH..MAX_STRENGTH);
-        Tag {
-            id,
-            antenna,
-            strength,
-        }
+        Tag::new(id, antenna, strength)
     }
+
+    /// Add newer reads of this token at the same antenna
+    fn update(&mut self, reads: Tag) {
+        self.strength = reads.strength;
+        self.peak_strength = self.peak_strength.max(reads.peak_strength);
+        self.first_seen = self.first_seen.min(reads.first_seen);
+        self.last_seen = self.last_seen.max(reads.last_seen);
+        self.read_count = self.read_count.saturating_add(reads.read_count);
+        self.received_at = self.received_at.min(reads.received_at);
+    }
+}
+
+pub(crate) fn now_micros() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_micros() as u64)
+        .unwrap_or_default()
 }
 
 impl Tag {
@@ -104,7 +151,18 @@ impl Tag {
         let antenna = tag_report_data.antenna_id.unwrap();
         let strength = tag_report_data.peak_rssi.unwrap();
 
-        Self::build(id, antenna, strength)
+        let mut tag = Self::build(id, antenna, strength)?;
+        // A report can cover multiple reads. We prefer the clock of the reader, since it saw the token.
+        if let Some(first_seen) = tag_report_data.first_seen_timestamp_utc {
+            tag.first_seen = first_seen;
+        }
+        if let Some(last_seen) = tag_report_data.last_seen_timestamp_utc {
+            tag.last_seen = last_seen;
+        }
+        if let Some(read_count) = tag_report_data.tag_seen_count {
+            tag.read_count = read_count.into();
+        }
+        Ok(tag)
     }
 }
 
@@ -116,8 +174,16 @@ impl TagsMap {
         TagsMap(HashMap::new())
     }
 
+    /// Add a read of a token
+    ///
+    /// If the token is still at the same antenna, we combine the reads. Otherwise the token moved and we start over.
     pub fn add_tag(&mut self, new_tag: Tag) {
-        self.0.insert(new_tag.clone().id, new_tag);
+        match self.0.get_mut(&new_tag.id) {
+            Some(tag) if tag.antenna == new_tag.antenna => tag.update(new_tag),
+            _ => {
+                self.0.insert(new_tag.clone().id, new_tag);
+            }
+        }
     }
 
     pub fn add_tags(&mut self, new_tags: Vec<Tag>) {
@@ -142,6 +208,10 @@ impl TagsMap {
         self.0.values()
     }
 
+    pub fn get(&self, id: &str) -> Option<&Tag> {
+        self.0.get(id)
+    }
+
     pub fn random(size: usize) -> Self {
         let tags = vec![Tag::random(); size];
         let mut new = Self::new();
@@ -193,11 +263,7 @@ mod tests {
 
     #[test]
     fn should_create_map_from_vector() {
-        let tag = Tag {
-            id: String::from("abc123"),
-            antenna: 1,
-            strength: -30,
-        };
+        let tag = Tag::new(String::from("abc123"), 1, -30);
         let mut tags = vec![tag];
 
         let map = TagsMap::from(tags.drain(..));
@@ -209,24 +275,61 @@ mod tests {
 
     #[test]
     fn should_replace_tag_when_updating() {
-        let tag1 = Tag {
-            id: String::from("abc123"),
-            antenna: 2,
-            strength: -35,
-        };
+        let tag1 = Tag::new(String::from("abc123"), 2, -35);
         let mut tags = vec![tag1];
 
         let mut map = TagsMap::from(tags.drain(..));
 
-        map.add_tag(Tag {
-            id: String::from("abc123"),
-            antenna: 1,
-            strength: -65,
-        });
+        map.add_tag(Tag::new(String::from("abc123"), 1, -65));
 
         assert_eq!(1, map.0.keys().len());
         assert_eq!(map.0.contains_key("abc123"), true);
         assert_eq!(map.0["abc123"].antenna, 1);
         assert_eq!(map.0["abc123"].strength, -65);
+        assert_eq!(map.0["abc123"].peak_strength, -65);
+        assert_eq!(map.0["abc123"].read_count, 1);
+    }
+
+    #[test]
+    fn should_combine_reads_at_same_antenna() {
+        let mut map = TagsMap::new();
+        map.add_tag(Tag {
+            first_seen: 100,
+            last_seen: 100,
+            ..Tag::new(String::from("abc123"), 2, -50)
+        });
+
+        map.add_tag(Tag {
+            first_seen: 200,
+            last_seen: 300,
+            read_count: 3,
+            ..Tag::new(String::from("abc123"), 2, -40)
+        });
+        map.add_tag(Tag {
+            first_seen: 400,
+            last_seen: 400,
+            ..Tag::new(String::from("abc123"), 2, -45)
+        });
+
+        let tag = map.get("abc123").unwrap();
+        assert_eq!(tag.strength, -45);
+        assert_eq!(tag.peak_strength, -40);
+        assert_eq!((tag.first_seen, tag.last_seen), (100, 400));
+        assert_eq!(tag.read_count, 5);
+    }
+
+    #[test]
+    fn should_use_timing_of_report() {
+        let mut tag_report = construct_tag_report(1, -30);
+        tag_report.first_seen_timestamp_utc = Some(1_000);
+        tag_report.last_seen_timestamp_utc = Some(2_000);
+        tag_report.tag_seen_count = Some(4);
+
+        let tag = Tag::from_report_data(tag_report).unwrap();
+
+        assert_eq!((tag.first_seen, tag.last_seen), (1_000, 2_000));
+        assert_eq!(tag.read_count, 4);
+        // The clock of the reader is never used for when we received the token
+        assert!(tag.received_at > 2_000);
     }
 }